@@ -0,0 +1,50 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mediasoup_server::signaling::OutgoingFrame;
+use mediasoup_server::SignalingMessage;
+use serde_json::json;
+use tokio_tungstenite::tungstenite::Message;
+
+const PEERS: usize = 20;
+
+fn audio_levels_notification() -> SignalingMessage {
+    SignalingMessage::notification(
+        "audioLevels".to_string(),
+        Some(json!({
+            "levels": (0..PEERS)
+                .map(|i| json!({ "userId": format!("user-{}", i), "volume": -40 - i as i32 }))
+                .collect::<Vec<_>>()
+        })),
+    )
+}
+
+fn broadcast(c: &mut Criterion) {
+    let message = audio_levels_notification();
+    let mut group = c.benchmark_group("broadcast_20_peers");
+    
+    // Previous behaviour: clone the message and serialize it for every peer
+    group.bench_function("serialize_per_peer", |b| {
+        b.iter(|| {
+            for _ in 0..PEERS {
+                let message = black_box(message.clone());
+                black_box(Message::Text(serde_json::to_string(&message).unwrap()));
+            }
+        })
+    });
+    
+    // Serialize once and share the frame between peers. Each outgoing task
+    // still copies the text into the owned `String` tungstenite sends.
+    group.bench_function("serialize_once", |b| {
+        b.iter(|| {
+            let frame = OutgoingFrame::new(black_box(&message)).unwrap();
+            for _ in 0..PEERS {
+                let frame = black_box(frame.clone());
+                black_box(Message::Text(frame.text.to_string()));
+            }
+        })
+    });
+    
+    group.finish();
+}
+
+criterion_group!(benches, broadcast);
+criterion_main!(benches);
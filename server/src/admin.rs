@@ -0,0 +1,160 @@
+use crate::auth::constant_time_eq;
+use crate::config::AdminConfig;
+use crate::error::{MediaSoupError, Result};
+use crate::room::{BanList, Room, RoomQueueMetrics};
+use crate::signaling::BannedUsersResponse;
+use dashmap::DashMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::info;
+use warp::http::StatusCode;
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+/// Name, type, help text and value of an exported metric
+type MetricFamily = (&'static str, &'static str, &'static str, fn(&RoomQueueMetrics) -> u64);
+
+/// Shared state of the admin API
+struct AdminState {
+    token: String,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    bans: Arc<BanList>,
+}
+
+/// Serve the admin API on `addr`, returning the bound address
+///
+/// Routes, all requiring `Authorization: Bearer <token>`:
+/// - `GET /admin/rooms/<room>/bans` lists banned user IDs
+/// - `PUT /admin/rooms/<room>/bans/<user>` bans a user and disconnects them;
+///   the room need not exist yet
+/// - `DELETE /admin/rooms/<room>/bans/<user>` lifts a ban
+/// - `GET /admin/metrics` reports outgoing queue counters in Prometheus text format
+pub fn spawn(
+    addr: SocketAddr,
+    config: &AdminConfig,
+    rooms: Arc<DashMap<String, Arc<Room>>>,
+    bans: Arc<BanList>,
+) -> Result<SocketAddr> {
+    let state = Arc::new(AdminState {
+        token: config.token.clone(),
+        rooms,
+        bans,
+    });
+    let with_state = warp::any().map(move || state.clone());
+    let authorization = warp::header::optional::<String>("authorization");
+    
+    let list_bans = warp::get()
+        .and(warp::path!("admin" / "rooms" / String / "bans"))
+        .and(authorization)
+        .and(with_state.clone())
+        .map(|room_id: String, authorization: Option<String>, state: Arc<AdminState>| {
+            with_authorization(&state, authorization, || {
+                let banned = BannedUsersResponse { user_ids: state.bans.banned_user_ids(&room_id) };
+                warp::reply::json(&banned).into_response()
+            })
+        });
+    
+    let ban = warp::put()
+        .and(warp::path!("admin" / "rooms" / String / "bans" / String))
+        .and(authorization)
+        .and(with_state.clone())
+        .map(|room_id: String, user_id: String, authorization: Option<String>, state: Arc<AdminState>| {
+            with_authorization(&state, authorization, || {
+                // A live room also disconnects the user's sessions
+                let result = match state.rooms.get(&room_id) {
+                    Some(room) => room.ban_user(&user_id, None),
+                    None => {
+                        state.bans.ban(&room_id, &user_id);
+                        info!("Banned user {} from room {}", user_id, room_id);
+                        Ok(())
+                    }
+                };
+                match result {
+                    Ok(()) => StatusCode::NO_CONTENT.into_response(),
+                    Err(e) => error_reply(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+                }
+            })
+        });
+    
+    let unban = warp::delete()
+        .and(warp::path!("admin" / "rooms" / String / "bans" / String))
+        .and(authorization)
+        .and(with_state.clone())
+        .map(|room_id: String, user_id: String, authorization: Option<String>, state: Arc<AdminState>| {
+            with_authorization(&state, authorization, || {
+                if state.bans.unban(&room_id, &user_id) {
+                    StatusCode::NO_CONTENT.into_response()
+                } else {
+                    error_reply(StatusCode::NOT_FOUND, "User is not banned")
+                }
+            })
+        });
+    
+    let metrics = warp::get()
+        .and(warp::path!("admin" / "metrics"))
+        .and(authorization)
+        .and(with_state)
+        .map(|authorization: Option<String>, state: Arc<AdminState>| {
+            if !is_authorized(&state, authorization) {
+                return error_reply(StatusCode::UNAUTHORIZED, "Invalid admin token");
+            }
+            warp::reply::with_header(render_metrics(&state), "content-type", "text/plain; version=0.0.4")
+                .into_response()
+        });
+    
+    let (bound_addr, server) = warp::serve(list_bans.or(ban).or(unban).or(metrics))
+        .try_bind_ephemeral(addr)
+        .map_err(|e| MediaSoupError::Config(format!("Failed to bind admin API to {}: {}", addr, e)))?;
+    tokio::spawn(server);
+    
+    info!("Admin API listening on {}", bound_addr);
+    Ok(bound_addr)
+}
+
+/// Check an `Authorization` header against the admin token
+fn is_authorized(state: &AdminState, authorization: Option<String>) -> bool {
+    let token = authorization.as_deref().and_then(|value| value.strip_prefix("Bearer "));
+    token.is_some_and(|token| constant_time_eq(token.as_bytes(), state.token.as_bytes()))
+}
+
+/// Check the bearer token and run `handler`
+fn with_authorization(
+    state: &AdminState,
+    authorization: Option<String>,
+    handler: impl FnOnce() -> Response,
+) -> Response {
+    if !is_authorized(state, authorization) {
+        return error_reply(StatusCode::UNAUTHORIZED, "Invalid admin token");
+    }
+    handler()
+}
+
+/// Render per-room outgoing queue metrics
+fn render_metrics(state: &AdminState) -> String {
+    let rooms: Vec<_> = state.rooms
+        .iter()
+        .map(|room| (room.id.clone(), room.queue_metrics()))
+        .collect();
+    
+    let mut body = String::new();
+    let families: [MetricFamily; 4] = [
+        ("mediasoup_room_peers", "gauge", "Peers in the room", |m| m.peers as u64),
+        ("mediasoup_outgoing_queue_depth", "gauge", "Messages waiting in outgoing queues", |m| m.depth as u64),
+        ("mediasoup_outgoing_queue_max_depth", "gauge", "Deepest outgoing queue in the room", |m| m.max_depth as u64),
+        ("mediasoup_outgoing_messages_dropped_total", "counter", "Outgoing messages dropped for slow peers", |m| m.dropped),
+    ];
+    for (name, kind, help, value) in families {
+        let _ = writeln!(body, "# HELP {} {}", name, help);
+        let _ = writeln!(body, "# TYPE {} {}", name, kind);
+        for (room_id, metrics) in &rooms {
+            let room_label = room_id.replace('\\', "\\\\").replace('"', "\\\"");
+            let _ = writeln!(body, "{}{{room=\"{}\"}} {}", name, room_label, value(metrics));
+        }
+    }
+    body
+}
+
+fn error_reply(status: StatusCode, error: &str) -> Response {
+    warp::reply::with_status(warp::reply::json(&serde_json::json!({ "error": error })), status).into_response()
+}
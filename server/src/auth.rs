@@ -0,0 +1,109 @@
+use crate::error::{MediaSoupError, Result};
+use crate::signaling::PeerRole;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Identity asserted by a signed join token
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthClaims {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    
+    #[serde(default)]
+    pub role: PeerRole,
+    
+    #[serde(rename = "displayName", default)]
+    pub display_name: Option<String>,
+    
+    /// Expiry as a unix timestamp in seconds
+    pub exp: u64,
+}
+
+/// Sign claims into a `<payload>.<signature>` token
+///
+/// The payload is the base64url JSON of the claims and the signature is the
+/// base64url HMAC-SHA256 of the payload keyed with the shared secret.
+pub fn issue_token(secret: &str, claims: &AuthClaims) -> Result<String> {
+    let payload = URL_SAFE_NO_PAD.encode(serde_json::to_vec(claims)?);
+    let signature = URL_SAFE_NO_PAD.encode(sign(secret, &payload).finalize().into_bytes());
+    Ok(format!("{}.{}", payload, signature))
+}
+
+/// Check a token's signature and expiry and return its claims
+pub fn verify_token(secret: &str, token: &str, now: SystemTime) -> Result<AuthClaims> {
+    let (payload, signature) = token
+        .split_once('.')
+        .ok_or_else(|| MediaSoupError::Unauthorized("Malformed token".to_string()))?;
+    let signature = URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|_| MediaSoupError::Unauthorized("Malformed token".to_string()))?;
+    sign(secret, payload)
+        .verify_slice(&signature)
+        .map_err(|_| MediaSoupError::Unauthorized("Invalid token signature".to_string()))?;
+    
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| MediaSoupError::Unauthorized("Malformed token".to_string()))?;
+    let claims: AuthClaims = serde_json::from_slice(&payload)
+        .map_err(|e| MediaSoupError::Unauthorized(format!("Invalid token claims: {}", e)))?;
+    
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if claims.exp <= now {
+        return Err(MediaSoupError::Unauthorized("Token expired".to_string()));
+    }
+    
+    Ok(claims)
+}
+
+/// Extract and percent-decode a parameter from a request query string
+pub fn query_param(query: Option<&str>, name: &str) -> Option<String> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| percent_decode(value))
+        .filter(|value| !value.is_empty())
+}
+
+/// Compare secrets without leaking the position of the first mismatch
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match (bytes[i], escaped) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn sign(secret: &str, payload: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
\ No newline at end of file
@@ -1,6 +1,8 @@
 use anyhow::Result;
+use crate::signaling::PeerRole;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::path::PathBuf;
 
 /// Server configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -11,6 +13,9 @@ pub struct Config {
     /// HTTP server address for serving static files (optional)
     pub http_addr: Option<SocketAddr>,
     
+    /// TLS settings for the WebSocket listener (optional)
+    pub tls: Option<TlsConfig>,
+    
     /// MediaSoup worker settings
     pub worker: WorkerConfig,
     
@@ -19,6 +24,36 @@ pub struct Config {
     
     /// WebRTC transport settings
     pub webrtc: WebRtcConfig,
+    
+    /// Signaling connection settings
+    pub signaling: SignalingConfig,
+    
+    /// Per-peer request and resource limits
+    pub limits: LimitsConfig,
+    
+    /// Room behaviour defaults
+    pub room: RoomConfig,
+    
+    /// TURN server credentials handed out to clients (optional)
+    pub turn: Option<TurnConfig>,
+    
+    /// Join token verification (optional; without it peers join anonymously as players)
+    pub auth: Option<AuthConfig>,
+    
+    /// Admin HTTP API on `http_addr` (optional)
+    pub admin: Option<AdminConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert_path: PathBuf,
+    
+    /// PEM private key
+    pub key_path: PathBuf,
+    
+    /// How often to check the certificate files for changes
+    pub reload_interval_secs: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,8 +89,44 @@ pub struct MediaCodec {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WebRtcConfig {
-    /// Listen IPs for WebRTC transports
+    /// Listen IPs for WebRTC transports (IPv4 and/or IPv6)
     pub listen_ips: Vec<ListenIp>,
+    
+    /// Gather UDP candidates
+    pub enable_udp: bool,
+    
+    /// Gather TCP candidates
+    pub enable_tcp: bool,
+    
+    /// Give UDP candidates priority over TCP
+    pub prefer_udp: bool,
+    
+    /// Close transports that are not connected this long after `connectTransport` or disconnection
+    pub transport_connect_timeout_secs: u64,
+    
+    /// Close transports the client never calls `connectTransport` on this long after creation
+    pub transport_idle_timeout_secs: u64,
+    
+    /// Cap in bps on media a client sends over one transport (optional)
+    pub max_incoming_bitrate: Option<u32>,
+    
+    /// Cap in bps on media sent to a client over one transport (optional)
+    pub max_outgoing_bitrate: Option<u32>,
+    
+    /// Total bps a room may send to clients, split across consumers (optional)
+    pub room_outgoing_bitrate: Option<u32>,
+    
+    /// Share one UDP and one TCP port per worker through a WebRtcServer (optional)
+    pub webrtc_server: Option<WebRtcServerConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebRtcServerConfig {
+    /// UDP port for the first worker; worker N listens on `udp_port + N`
+    pub udp_port: Option<u16>,
+    
+    /// TCP port for the first worker; worker N listens on `tcp_port + N`
+    pub tcp_port: Option<u16>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,6 +135,133 @@ pub struct ListenIp {
     pub announced_ip: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalingConfig {
+    /// Maximum number of messages buffered for a single peer
+    pub outgoing_queue_size: usize,
+    
+    /// How long a peer's outgoing queue may stay full before it is disconnected
+    pub slow_peer_timeout_ms: u64,
+    
+    /// Origins allowed to open the WebSocket (empty allows any)
+    pub allowed_origins: Vec<String>,
+    
+    /// Maximum number of open connections in total and per client IP
+    pub max_connections: usize,
+    pub max_connections_per_ip: usize,
+    
+    /// How long a client may take to finish the TLS handshake and WebSocket upgrade
+    pub handshake_timeout_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnConfig {
+    /// TURN/STUN URLs, e.g. `turn:turn.example.com:3478?transport=udp`
+    pub urls: Vec<String>,
+    
+    /// Shared secret configured as `static-auth-secret` in coturn
+    #[serde(skip_serializing)]
+    pub secret: String,
+    
+    /// Lifetime of issued credentials in seconds
+    pub ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    /// Shared secret used to sign join tokens
+    #[serde(skip_serializing)]
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminConfig {
+    /// Bearer token required on admin API requests
+    #[serde(skip_serializing)]
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LimitsConfig {
+    /// Maximum size of a single WebSocket message in bytes
+    pub max_message_size: usize,
+    
+    /// Sustained signaling requests allowed per second per peer
+    pub requests_per_second: f64,
+    
+    /// Number of requests a peer may send in a burst
+    pub request_burst: u32,
+    
+    /// Limit violations tolerated before a peer is disconnected
+    pub max_violations: u32,
+    
+    /// Maximum serialized size of a `broadcast`/`sendToPeer` payload in bytes
+    pub max_relay_payload_size: usize,
+    
+    /// Sustained relayed messages allowed per second per peer
+    pub relay_messages_per_second: f64,
+    
+    /// Number of relayed messages a peer may send in a burst
+    pub relay_burst: u32,
+    
+    /// Per-peer resource caps
+    pub max_transports_per_peer: usize,
+    pub max_producers_per_peer: usize,
+    pub max_consumers_per_peer: usize,
+    pub max_data_producers_per_peer: usize,
+    pub max_data_consumers_per_peer: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomConfig {
+    /// Forward video only from the N most recent active speakers (None forwards all)
+    pub last_n: Option<usize>,
+    
+    /// How often the active speaker observer checks audio volumes
+    pub active_speaker_interval_ms: u16,
+    
+    /// How often `audioLevels` notifications are sent while someone is speaking
+    pub audio_level_interval_ms: u16,
+    
+    /// Volume in dBov below which a producer counts as silent
+    pub audio_level_threshold: i8,
+    
+    /// Minimum time between keyframe requests forwarded to one producer
+    pub key_frame_request_interval_ms: u64,
+    
+    /// What happens when a user joins a room they are already connected to
+    pub duplicate_sessions: DuplicateSessionPolicy,
+    
+    /// Hold joining peers in a lobby until a GM admits them
+    pub lobby: bool,
+    
+    /// Password required to join, except for GMs (optional)
+    #[serde(skip_serializing)]
+    pub password: Option<String>,
+    
+    /// Maximum peers in a room, not counting GMs (None is unlimited)
+    pub max_peers: Option<usize>,
+    
+    /// Maximum peers publishing video at once (None is unlimited)
+    pub max_video_publishers: Option<usize>,
+    
+    /// Roles allowed to publish video
+    pub video_publish_roles: Vec<PeerRole>,
+    
+    /// Announce spectators to players; GMs always see them
+    pub show_spectators: bool,
+}
+
+/// Handling of a second connection from the same user in a room
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateSessionPolicy {
+    /// Disconnect the existing session in favour of the new one
+    Replace,
+    /// Refuse the new connection
+    Reject,
+}
+
 impl Config {
     /// Load configuration from environment variables and defaults
     pub fn load() -> Result<Self> {
@@ -77,6 +275,22 @@ impl Config {
                 .map(|addr| addr.parse())
                 .transpose()?,
             
+            tls: match (
+                std::env::var("MEDIASOUP_TLS_CERT").ok(),
+                std::env::var("MEDIASOUP_TLS_KEY").ok(),
+            ) {
+                (Some(cert_path), Some(key_path)) => Some(TlsConfig {
+                    cert_path: cert_path.into(),
+                    key_path: key_path.into(),
+                    reload_interval_secs: std::env::var("MEDIASOUP_TLS_RELOAD_INTERVAL_SECS")
+                        .unwrap_or_else(|_| "60".to_string())
+                        .parse()
+                        .unwrap_or(60),
+                }),
+                (None, None) => None,
+                _ => anyhow::bail!("MEDIASOUP_TLS_CERT and MEDIASOUP_TLS_KEY must be set together"),
+            },
+            
             worker: WorkerConfig {
                 num_workers: std::env::var("MEDIASOUP_NUM_WORKERS")
                     .unwrap_or_else(|_| "1".to_string())
@@ -108,18 +322,287 @@ impl Config {
             },
             
             webrtc: WebRtcConfig {
-                listen_ips: vec![
-                    ListenIp {
-                        ip: "0.0.0.0".to_string(),
-                        announced_ip: std::env::var("MEDIASOUP_ANNOUNCED_IP").ok(),
-                    }
-                ],
+                listen_ips: match std::env::var("MEDIASOUP_LISTEN_IPS") {
+                    Ok(listen_ips) => Self::parse_listen_ips(&listen_ips),
+                    Err(_) => vec![
+                        ListenIp {
+                            ip: "0.0.0.0".to_string(),
+                            announced_ip: std::env::var("MEDIASOUP_ANNOUNCED_IP").ok(),
+                        }
+                    ],
+                },
+                
+                enable_udp: std::env::var("MEDIASOUP_ENABLE_UDP")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .unwrap_or(true),
+                
+                enable_tcp: std::env::var("MEDIASOUP_ENABLE_TCP")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .unwrap_or(true),
+                
+                prefer_udp: std::env::var("MEDIASOUP_PREFER_UDP")
+                    .unwrap_or_else(|_| "true".to_string())
+                    .parse()
+                    .unwrap_or(true),
+                
+                transport_connect_timeout_secs: std::env::var("MEDIASOUP_TRANSPORT_CONNECT_TIMEOUT_SECS")
+                    .unwrap_or_else(|_| "30".to_string())
+                    .parse()
+                    .unwrap_or(30),
+                
+                transport_idle_timeout_secs: std::env::var("MEDIASOUP_TRANSPORT_IDLE_TIMEOUT_SECS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .unwrap_or(300),
+                
+                max_incoming_bitrate: std::env::var("MEDIASOUP_MAX_INCOMING_BITRATE")
+                    .ok()
+                    .map(|bitrate| bitrate.parse())
+                    .transpose()?,
+                
+                max_outgoing_bitrate: std::env::var("MEDIASOUP_MAX_OUTGOING_BITRATE")
+                    .ok()
+                    .map(|bitrate| bitrate.parse())
+                    .transpose()?,
+                
+                room_outgoing_bitrate: std::env::var("MEDIASOUP_ROOM_OUTGOING_BITRATE")
+                    .ok()
+                    .map(|bitrate| bitrate.parse())
+                    .transpose()?,
+                
+                webrtc_server: match (
+                    std::env::var("MEDIASOUP_WEBRTC_SERVER_UDP_PORT").ok(),
+                    std::env::var("MEDIASOUP_WEBRTC_SERVER_TCP_PORT").ok(),
+                ) {
+                    (None, None) => None,
+                    (udp_port, tcp_port) => Some(WebRtcServerConfig {
+                        udp_port: udp_port.map(|port| port.parse()).transpose()?,
+                        tcp_port: tcp_port.map(|port| port.parse()).transpose()?,
+                    }),
+                },
+            },
+            
+            signaling: SignalingConfig {
+                outgoing_queue_size: std::env::var("MEDIASOUP_OUTGOING_QUEUE_SIZE")
+                    .unwrap_or_else(|_| "256".to_string())
+                    .parse()
+                    .unwrap_or(256),
+                
+                slow_peer_timeout_ms: std::env::var("MEDIASOUP_SLOW_PEER_TIMEOUT_MS")
+                    .unwrap_or_else(|_| "10000".to_string())
+                    .parse()
+                    .unwrap_or(10000),
+                
+                allowed_origins: std::env::var("MEDIASOUP_ALLOWED_ORIGINS")
+                    .unwrap_or_default()
+                    .split(',')
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect(),
+                
+                max_connections: std::env::var("MEDIASOUP_MAX_CONNECTIONS")
+                    .unwrap_or_else(|_| "500".to_string())
+                    .parse()
+                    .unwrap_or(500),
+                
+                max_connections_per_ip: std::env::var("MEDIASOUP_MAX_CONNECTIONS_PER_IP")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                
+                handshake_timeout_ms: std::env::var("MEDIASOUP_HANDSHAKE_TIMEOUT_MS")
+                    .unwrap_or_else(|_| "10000".to_string())
+                    .parse()
+                    .unwrap_or(10000),
             },
+            
+            limits: LimitsConfig {
+                max_message_size: std::env::var("MEDIASOUP_MAX_MESSAGE_SIZE")
+                    .unwrap_or_else(|_| "65536".to_string())
+                    .parse()
+                    .unwrap_or(65536),
+                
+                requests_per_second: std::env::var("MEDIASOUP_REQUESTS_PER_SECOND")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20.0),
+                
+                request_burst: std::env::var("MEDIASOUP_REQUEST_BURST")
+                    .unwrap_or_else(|_| "50".to_string())
+                    .parse()
+                    .unwrap_or(50),
+                
+                max_violations: std::env::var("MEDIASOUP_MAX_VIOLATIONS")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                
+                max_relay_payload_size: std::env::var("MEDIASOUP_MAX_RELAY_PAYLOAD_SIZE")
+                    .unwrap_or_else(|_| "16384".to_string())
+                    .parse()
+                    .unwrap_or(16384),
+                
+                relay_messages_per_second: std::env::var("MEDIASOUP_RELAY_MESSAGES_PER_SECOND")
+                    .unwrap_or_else(|_| "10".to_string())
+                    .parse()
+                    .unwrap_or(10.0),
+                
+                relay_burst: std::env::var("MEDIASOUP_RELAY_BURST")
+                    .unwrap_or_else(|_| "20".to_string())
+                    .parse()
+                    .unwrap_or(20),
+                
+                max_transports_per_peer: std::env::var("MEDIASOUP_MAX_TRANSPORTS_PER_PEER")
+                    .unwrap_or_else(|_| "4".to_string())
+                    .parse()
+                    .unwrap_or(4),
+                
+                max_producers_per_peer: std::env::var("MEDIASOUP_MAX_PRODUCERS_PER_PEER")
+                    .unwrap_or_else(|_| "8".to_string())
+                    .parse()
+                    .unwrap_or(8),
+                
+                max_consumers_per_peer: std::env::var("MEDIASOUP_MAX_CONSUMERS_PER_PEER")
+                    .unwrap_or_else(|_| "200".to_string())
+                    .parse()
+                    .unwrap_or(200),
+                
+                max_data_producers_per_peer: std::env::var("MEDIASOUP_MAX_DATA_PRODUCERS_PER_PEER")
+                    .unwrap_or_else(|_| "4".to_string())
+                    .parse()
+                    .unwrap_or(4),
+                
+                max_data_consumers_per_peer: std::env::var("MEDIASOUP_MAX_DATA_CONSUMERS_PER_PEER")
+                    .unwrap_or_else(|_| "100".to_string())
+                    .parse()
+                    .unwrap_or(100),
+            },
+            
+            room: RoomConfig {
+                last_n: std::env::var("MEDIASOUP_LAST_N")
+                    .ok()
+                    .map(|last_n| last_n.parse())
+                    .transpose()?,
+                
+                active_speaker_interval_ms: std::env::var("MEDIASOUP_ACTIVE_SPEAKER_INTERVAL_MS")
+                    .unwrap_or_else(|_| "300".to_string())
+                    .parse()
+                    .unwrap_or(300),
+                
+                audio_level_interval_ms: std::env::var("MEDIASOUP_AUDIO_LEVEL_INTERVAL_MS")
+                    .unwrap_or_else(|_| "500".to_string())
+                    .parse()
+                    .unwrap_or(500),
+                
+                audio_level_threshold: std::env::var("MEDIASOUP_AUDIO_LEVEL_THRESHOLD")
+                    .unwrap_or_else(|_| "-70".to_string())
+                    .parse()
+                    .unwrap_or(-70),
+                
+                key_frame_request_interval_ms: std::env::var("MEDIASOUP_KEY_FRAME_REQUEST_INTERVAL_MS")
+                    .unwrap_or_else(|_| "1000".to_string())
+                    .parse()
+                    .unwrap_or(1000),
+                
+                duplicate_sessions: match std::env::var("MEDIASOUP_DUPLICATE_SESSIONS")
+                    .unwrap_or_else(|_| "replace".to_string())
+                    .as_str()
+                {
+                    "replace" => DuplicateSessionPolicy::Replace,
+                    "reject" => DuplicateSessionPolicy::Reject,
+                    other => anyhow::bail!("MEDIASOUP_DUPLICATE_SESSIONS must be replace or reject, got {}", other),
+                },
+                
+                lobby: std::env::var("MEDIASOUP_LOBBY")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse()
+                    .unwrap_or(false),
+                
+                password: std::env::var("MEDIASOUP_ROOM_PASSWORD")
+                    .ok()
+                    .filter(|password| !password.is_empty()),
+                
+                max_peers: std::env::var("MEDIASOUP_ROOM_MAX_PEERS")
+                    .ok()
+                    .map(|max| max.parse())
+                    .transpose()?,
+                
+                max_video_publishers: std::env::var("MEDIASOUP_ROOM_MAX_VIDEO_PUBLISHERS")
+                    .ok()
+                    .map(|max| max.parse())
+                    .transpose()?,
+                
+                video_publish_roles: std::env::var("MEDIASOUP_VIDEO_PUBLISH_ROLES")
+                    .unwrap_or_else(|_| "player,gm".to_string())
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|role| !role.is_empty())
+                    .map(|role| serde_json::from_value(serde_json::Value::String(role.to_string())))
+                    .collect::<std::result::Result<_, _>>()?,
+                
+                show_spectators: std::env::var("MEDIASOUP_SHOW_SPECTATORS")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse()
+                    .unwrap_or(false),
+            },
+            
+            turn: match (
+                std::env::var("MEDIASOUP_TURN_URLS").ok(),
+                std::env::var("MEDIASOUP_TURN_SECRET").ok(),
+            ) {
+                (Some(urls), Some(secret)) => Some(TurnConfig {
+                    urls: urls
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect(),
+                    secret,
+                    ttl_secs: std::env::var("MEDIASOUP_TURN_TTL_SECS")
+                        .unwrap_or_else(|_| "86400".to_string())
+                        .parse()
+                        .unwrap_or(86400),
+                }),
+                (None, None) => None,
+                _ => anyhow::bail!("MEDIASOUP_TURN_URLS and MEDIASOUP_TURN_SECRET must be set together"),
+            },
+            
+            auth: std::env::var("MEDIASOUP_AUTH_SECRET")
+                .ok()
+                .map(|secret| AuthConfig { secret }),
+            
+            admin: std::env::var("MEDIASOUP_ADMIN_TOKEN")
+                .ok()
+                .map(|token| AdminConfig { token }),
         };
         
+        if config.room.lobby && config.auth.is_none() {
+            anyhow::bail!("MEDIASOUP_LOBBY requires MEDIASOUP_AUTH_SECRET so a GM can admit peers");
+        }
+        
         Ok(config)
     }
     
+    /// Parse `ip[=announced],...` entries, e.g. `0.0.0.0=203.0.113.5,::`
+    pub fn parse_listen_ips(value: &str) -> Vec<ListenIp> {
+        value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| match entry.split_once('=') {
+                Some((ip, announced_ip)) => ListenIp {
+                    ip: ip.trim().to_string(),
+                    announced_ip: Some(announced_ip.trim().to_string()),
+                },
+                None => ListenIp {
+                    ip: entry.to_string(),
+                    announced_ip: None,
+                },
+            })
+            .collect()
+    }
+    
     /// Default media codecs for FoundryVTT compatibility
     fn default_media_codecs() -> Vec<MediaCodec> {
         vec![
@@ -10,6 +10,9 @@ pub enum MediaSoupError {
     #[error("MediaSoup create router error: {0}")]
     CreateRouter(#[from] mediasoup::worker::CreateRouterError),
     
+    #[error("MediaSoup create WebRTC server error: {0}")]
+    CreateWebRtcServer(#[from] mediasoup::worker::CreateWebRtcServerError),
+    
     #[error("MediaSoup request error: {0}")]
     Request(#[from] mediasoup::worker::RequestError),
     
@@ -22,8 +25,13 @@ pub enum MediaSoupError {
     #[error("MediaSoup consumer error: {0}")]
     Consumer(String),
     
+    /// Boxed so tungstenite's large error type doesn't widen every
+    /// `Result<T, MediaSoupError>` (clippy's `result_large_err`)
     #[error("WebSocket error: {0}")]
-    WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+    
+    #[error("TLS error: {0}")]
+    Tls(String),
     
     #[error("JSON serialization error: {0}")]
     Json(#[from] serde_json::Error),
@@ -40,9 +48,39 @@ pub enum MediaSoupError {
     #[error("Producer not found: {0}")]
     ProducerNotFound(String),
     
+    #[error("Data producer not found: {0}")]
+    DataProducerNotFound(String),
+    
     #[error("Consumer not found: {0}")]
     ConsumerNotFound(String),
     
+    #[error("Peer outgoing queue full: {0}")]
+    QueueFull(String),
+    
+    #[error("Rate limit exceeded: {0}")]
+    RateLimited(String),
+    
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(String),
+    
+    #[error("Data consumer limit reached: {0}")]
+    DataConsumerLimit(String),
+    
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    
+    #[error("Room full: {0}")]
+    RoomFull(String),
+    
+    #[error("Publish not allowed: {0}")]
+    PublishNotAllowed(String),
+    
+    #[error("Publish limit reached: {0}")]
+    PublishLimit(String),
+    
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
     
@@ -50,4 +88,44 @@ pub enum MediaSoupError {
     Config(String),
 }
 
+impl MediaSoupError {
+    /// Stable code sent to clients alongside the error message
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::RoomNotFound(_) => "ROOM_NOT_FOUND",
+            Self::PeerNotFound(_) => "PEER_NOT_FOUND",
+            Self::TransportNotFound(_) => "TRANSPORT_NOT_FOUND",
+            Self::ProducerNotFound(_) => "PRODUCER_NOT_FOUND",
+            Self::DataProducerNotFound(_) => "DATA_PRODUCER_NOT_FOUND",
+            Self::ConsumerNotFound(_) => "CONSUMER_NOT_FOUND",
+            Self::QueueFull(_) => "QUEUE_FULL",
+            Self::RateLimited(_) => "RATE_LIMITED",
+            Self::LimitExceeded(_) => "LIMIT_EXCEEDED",
+            Self::DataConsumerLimit(_) => "DATA_CONSUMER_LIMIT",
+            Self::Unauthorized(_) => "UNAUTHORIZED",
+            Self::Forbidden(_) => "FORBIDDEN",
+            Self::RoomFull(_) => "ROOM_FULL",
+            Self::PublishNotAllowed(_) => "PUBLISH_NOT_ALLOWED",
+            Self::PublishLimit(_) => "PUBLISH_LIMIT",
+            Self::InvalidRequest(_) | Self::Json(_) => "INVALID_REQUEST",
+            Self::Io(_)
+            | Self::CreateRouter(_)
+            | Self::CreateWebRtcServer(_)
+            | Self::Request(_)
+            | Self::Transport(_)
+            | Self::Producer(_)
+            | Self::Consumer(_)
+            | Self::WebSocket(_)
+            | Self::Tls(_)
+            | Self::Config(_) => "INTERNAL_ERROR",
+        }
+    }
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for MediaSoupError {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        Self::WebSocket(Box::new(error))
+    }
+}
+
 pub type Result<T> = std::result::Result<T, MediaSoupError>;
\ No newline at end of file
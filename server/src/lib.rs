@@ -1,8 +1,13 @@
+pub mod admin;
+pub mod auth;
 pub mod config;
 pub mod error;
+pub mod limits;
 pub mod room;
 pub mod server;
 pub mod signaling;
+pub mod tls;
+pub mod turn;
 
 pub use config::Config;
 pub use error::{MediaSoupError, Result};
@@ -0,0 +1,270 @@
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::protocol::WebSocketConfig;
+
+/// Token bucket rate limiter for signaling requests
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rate_per_sec: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `rate_per_sec` requests on average with bursts up to `burst`
+    pub fn new(rate_per_sec: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            rate_per_sec,
+            burst,
+            tokens: burst,
+            last_refill: Instant::now(),
+        }
+    }
+    
+    /// Try to take a token now
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+    
+    /// Try to take a token at the given instant
+    pub fn try_acquire_at(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate_per_sec).min(self.burst);
+        self.last_refill = now;
+        
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// When a throttled action may run, as decided by `KeyedThrottle::acquire_or_defer`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Deferral {
+    /// Run it now
+    Now,
+    /// Run it after this delay, when the interval ends
+    After(Duration),
+    /// An action is already scheduled for the end of the interval and covers this one
+    Scheduled,
+}
+
+/// Allows one action per key within an interval, e.g. keyframe requests per producer
+#[derive(Debug)]
+pub struct KeyedThrottle {
+    interval: Duration,
+    last: DashMap<String, Instant>,
+}
+
+impl KeyedThrottle {
+    /// Create a throttle allowing one action per key every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last: DashMap::new(),
+        }
+    }
+    
+    /// Try to act on `key` now
+    pub fn try_acquire(&self, key: &str) -> bool {
+        self.try_acquire_at(key, Instant::now())
+    }
+    
+    /// Try to act on `key` at the given instant
+    pub fn try_acquire_at(&self, key: &str, now: Instant) -> bool {
+        match self.last.entry(key.to_string()) {
+            Entry::Occupied(mut last) => {
+                if now.saturating_duration_since(*last.get()) < self.interval {
+                    return false;
+                }
+                last.insert(now);
+            }
+            Entry::Vacant(last) => {
+                last.insert(now);
+            }
+        }
+        true
+    }
+    
+    /// Act on `key` now or, within the interval, once when it ends
+    ///
+    /// Unlike `try_acquire`, nothing is dropped: callers that arrive while an
+    /// action is pending are covered by it.
+    pub fn acquire_or_defer(&self, key: &str) -> Deferral {
+        self.acquire_or_defer_at(key, Instant::now())
+    }
+    
+    /// Act on `key` at the given instant or schedule it for the end of the interval
+    pub fn acquire_or_defer_at(&self, key: &str, now: Instant) -> Deferral {
+        match self.last.entry(key.to_string()) {
+            Entry::Occupied(mut last) => {
+                let last_at = *last.get();
+                if last_at > now {
+                    return Deferral::Scheduled;
+                }
+                if now.duration_since(last_at) >= self.interval {
+                    last.insert(now);
+                    return Deferral::Now;
+                }
+                let next = last_at + self.interval;
+                last.insert(next);
+                Deferral::After(next - now)
+            }
+            Entry::Vacant(last) => {
+                last.insert(now);
+                Deferral::Now
+            }
+        }
+    }
+    
+    /// Forget a key that no longer exists
+    pub fn remove(&self, key: &str) {
+        self.last.remove(key);
+    }
+}
+
+/// Reason a connection was refused before the WebSocket upgrade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionRejection {
+    TooManyConnections,
+    TooManyConnectionsFromIp,
+}
+
+impl ConnectionRejection {
+    /// HTTP status answered to the rejected upgrade
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::TooManyConnections => StatusCode::SERVICE_UNAVAILABLE,
+            Self::TooManyConnectionsFromIp => StatusCode::TOO_MANY_REQUESTS,
+        }
+    }
+    
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::TooManyConnections => "Server connection limit reached",
+            Self::TooManyConnectionsFromIp => "Too many connections from this address",
+        }
+    }
+}
+
+/// Tracks open connections in total and per client IP
+#[derive(Debug)]
+pub struct ConnectionTracker {
+    max_total: usize,
+    max_per_ip: usize,
+    total: AtomicUsize,
+    per_ip: DashMap<IpAddr, usize>,
+}
+
+impl ConnectionTracker {
+    pub fn new(max_total: usize, max_per_ip: usize) -> Arc<Self> {
+        Arc::new(Self {
+            max_total,
+            max_per_ip,
+            total: AtomicUsize::new(0),
+            per_ip: DashMap::new(),
+        })
+    }
+    
+    /// Reserve a connection slot, released when the guard is dropped
+    pub fn try_acquire(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionGuard, ConnectionRejection> {
+        let mut count = self.per_ip.entry(ip).or_insert(0);
+        if *count >= self.max_per_ip {
+            return Err(ConnectionRejection::TooManyConnectionsFromIp);
+        }
+        
+        if self.total
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |total| (total < self.max_total).then_some(total + 1))
+            .is_err()
+        {
+            let empty = *count == 0;
+            drop(count);
+            if empty {
+                self.per_ip.remove_if(&ip, |_, count| *count == 0);
+            }
+            return Err(ConnectionRejection::TooManyConnections);
+        }
+        
+        *count += 1;
+        Ok(ConnectionGuard {
+            tracker: self.clone(),
+            ip,
+        })
+    }
+    
+    /// Number of open connections
+    pub fn total(&self) -> usize {
+        self.total.load(Ordering::Acquire)
+    }
+    
+    /// Number of open connections from one IP
+    pub fn count_for_ip(&self, ip: IpAddr) -> usize {
+        self.per_ip.get(&ip).map(|count| *count).unwrap_or(0)
+    }
+}
+
+/// Connection slot held for the lifetime of a connection
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    tracker: Arc<ConnectionTracker>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.tracker.total.fetch_sub(1, Ordering::AcqRel);
+        if let Some(mut count) = self.tracker.per_ip.get_mut(&self.ip) {
+            *count = count.saturating_sub(1);
+        }
+        self.tracker.per_ip.remove_if(&self.ip, |_, count| *count == 0);
+    }
+}
+
+/// Wait for a TLS handshake or WebSocket upgrade, giving up after `timeout`
+///
+/// Returns `None` if the client stalled. The caller then drops the stream and
+/// its `ConnectionGuard`, so idle sockets can't hold connection slots.
+pub async fn complete_handshake<F: Future>(timeout: Duration, handshake: F) -> Option<F::Output> {
+    tokio::time::timeout(timeout, handshake).await.ok()
+}
+
+/// WebSocket settings capping incoming frames and messages at `max_message_size` bytes
+///
+/// Larger input fails the read with a capacity error, which closes the connection.
+pub fn websocket_config(max_message_size: usize) -> WebSocketConfig {
+    WebSocketConfig {
+        max_message_size: Some(max_message_size),
+        max_frame_size: Some(max_message_size),
+        ..Default::default()
+    }
+}
+
+/// Check an upgrade request's Origin header against the allowlist
+///
+/// An empty allowlist accepts any origin. Otherwise the Origin must match an
+/// entry exactly (case-insensitive) or the list must contain `*`.
+pub fn is_origin_allowed(allowed_origins: &[String], origin: Option<&str>) -> bool {
+    if allowed_origins.is_empty() {
+        return true;
+    }
+    
+    let Some(origin) = origin else {
+        return false;
+    };
+    let origin = origin.trim_end_matches('/');
+    
+    allowed_origins.iter().any(|allowed| {
+        allowed == "*" || allowed.trim_end_matches('/').eq_ignore_ascii_case(origin)
+    })
+}
@@ -1,31 +1,44 @@
 use anyhow::Result;
 use std::net::SocketAddr;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
 
+mod admin;
+mod auth;
 mod config;
 mod error;
+mod limits;
 mod server;
 mod signaling;
+mod tls;
+mod turn;
 mod room;
 
+use auth::AuthClaims;
 use config::Config;
 use server::MediaSoupServer;
+use signaling::PeerRole;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("issue-token") {
+        return issue_token(&args[1..]);
+    }
+    
     // Initialize tracing
     let subscriber = FmtSubscriber::builder()
         .with_max_level(Level::INFO)
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
-
+    
     info!("Starting MediaSoup server for FoundryVTT");
-
+    
     // Load configuration
     let config = Config::load()?;
     info!("Loaded configuration: listening on {}", config.listen_addr);
-
+    
     // Create and start the server
     let server = MediaSoupServer::new(config).await?;
     
@@ -33,6 +46,33 @@ async fn main() -> Result<()> {
     
     // Run the server
     server.run().await?;
+    
+    Ok(())
+}
 
+/// Print a join token: `issue-token <user id> [player|gm|spectator] [ttl secs]`
+fn issue_token(args: &[String]) -> Result<()> {
+    let secret = std::env::var("MEDIASOUP_AUTH_SECRET")
+        .map_err(|_| anyhow::anyhow!("MEDIASOUP_AUTH_SECRET must be set to issue tokens"))?;
+    let user_id = args
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("Usage: issue-token <user id> [player|gm|spectator] [ttl secs]"))?;
+    let role: PeerRole = match args.get(1) {
+        Some(role) => serde_json::from_value(serde_json::Value::String(role.clone()))?,
+        None => PeerRole::default(),
+    };
+    let ttl_secs: u64 = match args.get(2) {
+        Some(ttl) => ttl.parse()?,
+        None => 86400,
+    };
+    
+    let claims = AuthClaims {
+        user_id: user_id.clone(),
+        role,
+        display_name: None,
+        exp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() + ttl_secs,
+    };
+    println!("{}", auth::issue_token(&secret, &claims)?);
+    
     Ok(())
 }
\ No newline at end of file
@@ -1,17 +1,60 @@
-use crate::config::ListenIp;
+use crate::auth::constant_time_eq;
+use crate::config::{DuplicateSessionPolicy, ListenIp, RoomConfig};
 use crate::error::{MediaSoupError, Result};
-use crate::signaling::{NewProducerNotification, ProducerClosedNotification, SignalingMessage};
+use crate::limits::{Deferral, KeyedThrottle, RateLimiter};
+use crate::signaling::{
+    ActiveSpeakerNotification, AudioLevel, AudioLevelsNotification, ConsumerLayersChangedNotification,
+    ConsumerPausedNotification, DataProducerClosedNotification, ModeratedNotification, ModerationAction,
+    JoinRequestCancelledNotification, NewDataProducerNotification, NewProducerNotification, OutgoingFrame, PeerDevices, PeerInfo, PeerLeftNotification, PeerMessageNotification, PeerRole,
+    ProducerClosedNotification, SignalingMessage,
+    TransportClosedNotification, TransportStateChangedNotification,
+};
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
 use mediasoup::prelude::*;
-use mediasoup::data_structures::{ListenInfo, Protocol, AppData};
+use mediasoup::data_structures::{AppData, DtlsState, IceState, ListenInfo, Protocol};
 use mediasoup::rtp_parameters::RtpCapabilitiesFinalized;
 use serde_json::Value;
-use std::sync::Arc;
-use std::num::{NonZeroU32, NonZeroU8};
-use tokio::sync::mpsc;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::num::{NonZeroU16, NonZeroU32, NonZeroU8};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Notify;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+/// Lowest non-zero outgoing bitrate mediasoup accepts for a transport
+const MIN_OUTGOING_BITRATE: u32 = 30_000;
+
+/// Most producers reported in one `audioLevels` notification
+const MAX_AUDIO_LEVEL_ENTRIES: u16 = 16;
+
+/// Most simulcast encodings accepted for one video producer
+pub const MAX_SIMULCAST_ENCODINGS: usize = 4;
+
+/// Snapshot of a peer's outgoing message queue
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutgoingQueueStats {
+    pub depth: usize,
+    pub capacity: usize,
+    pub high_water_mark: usize,
+    pub dropped: u64,
+}
+
+/// Outgoing queue counters summed over a room's peers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoomQueueMetrics {
+    pub peers: usize,
+    pub depth: usize,
+    pub max_depth: usize,
+    /// Messages dropped since the room was created, including by peers that left
+    pub dropped: u64,
+}
+
 /// Represents a peer connection in a room
 #[derive(Debug)]
 pub struct Peer {
@@ -20,101 +63,1704 @@ pub struct Peer {
     pub transports: DashMap<String, WebRtcTransport>,
     pub producers: DashMap<String, Producer>,
     pub consumers: DashMap<String, Consumer>,
-    pub message_sender: mpsc::UnboundedSender<SignalingMessage>,
+    pub data_producers: DashMap<String, DataProducer>,
+    pub data_consumers: DashMap<String, DataConsumer>,
+    info: Mutex<PeerInfo>,
+    joined_at: Instant,
+    /// Audio is paused by a GM and may not be resumed by the peer
+    muted: AtomicBool,
+    /// The join token set the display name, so the client may not change it
+    display_name_locked: AtomicBool,
+    pub message_sender: mpsc::Sender<OutgoingFrame>,
+    slow_peer_timeout: Duration,
+    stalled_since: Mutex<Option<Instant>>,
+    high_water_mark: AtomicUsize,
+    dropped_messages: AtomicU64,
+    limit_violations: AtomicU32,
+    /// Transports the client has called `connectTransport` on
+    connect_requested: Mutex<HashSet<String>>,
+    /// Signaling requests, and `broadcast`/`sendToPeer` relays on top of them
+    request_limiter: Mutex<RateLimiter>,
+    relay_limiter: Mutex<RateLimiter>,
+    disconnecting: AtomicBool,
+    disconnect: Notify,
+    last_n: Mutex<Option<usize>>,
+    pinned_user_ids: Mutex<HashSet<String>>,
+    /// Consumers waiting for the client's `resumeConsumer`
+    client_paused: Mutex<HashSet<String>>,
+    /// Consumers held back by last-N
+    last_n_paused: Mutex<HashSet<String>>,
 }
 
 impl Peer {
-    pub fn new(user_id: String, message_sender: mpsc::UnboundedSender<SignalingMessage>) -> Self {
+    pub fn new(
+        user_id: String,
+        message_sender: mpsc::Sender<OutgoingFrame>,
+        slow_peer_timeout: Duration,
+        request_limiter: RateLimiter,
+        relay_limiter: RateLimiter,
+    ) -> Self {
         Self {
             id: Uuid::new_v4().to_string(),
+            info: Mutex::new(PeerInfo {
+                user_id: user_id.clone(),
+                display_name: None,
+                role: PeerRole::default(),
+                devices: PeerDevices::default(),
+            }),
             user_id,
+            joined_at: Instant::now(),
+            muted: AtomicBool::new(false),
+            display_name_locked: AtomicBool::new(false),
             transports: DashMap::new(),
             producers: DashMap::new(),
             consumers: DashMap::new(),
+            data_producers: DashMap::new(),
+            data_consumers: DashMap::new(),
             message_sender,
+            slow_peer_timeout,
+            stalled_since: Mutex::new(None),
+            high_water_mark: AtomicUsize::new(0),
+            dropped_messages: AtomicU64::new(0),
+            limit_violations: AtomicU32::new(0),
+            connect_requested: Mutex::new(HashSet::new()),
+            request_limiter: Mutex::new(request_limiter),
+            relay_limiter: Mutex::new(relay_limiter),
+            disconnecting: AtomicBool::new(false),
+            disconnect: Notify::new(),
+            last_n: Mutex::new(None),
+            pinned_user_ids: Mutex::new(HashSet::new()),
+            client_paused: Mutex::new(HashSet::new()),
+            last_n_paused: Mutex::new(HashSet::new()),
+        }
+    }
+    
+    /// When the peer connected
+    pub fn joined_at(&self) -> Instant {
+        self.joined_at
+    }
+    
+    /// Participant details shared with the rest of the room
+    pub fn info(&self) -> PeerInfo {
+        self.info.lock().unwrap().clone()
+    }
+    
+    /// Set the display name from the join token; the client can no longer change it
+    pub fn set_token_display_name(&self, display_name: String) {
+        self.info.lock().unwrap().display_name = Some(display_name);
+        self.display_name_locked.store(true, Ordering::Relaxed);
+    }
+    
+    /// Apply a client's info update, returning the new info
+    ///
+    /// A display name set by the join token may not be changed.
+    pub fn update_info(&self, display_name: Option<String>, devices: Option<PeerDevices>) -> Result<PeerInfo> {
+        let mut info = self.info.lock().unwrap();
+        if self.display_name_locked.load(Ordering::Relaxed)
+            && display_name.is_some()
+            && display_name != info.display_name
+        {
+            return Err(MediaSoupError::Forbidden("Display name is set by the join token".to_string()));
+        }
+        if let Some(display_name) = display_name {
+            info.display_name = Some(display_name);
+        }
+        if let Some(devices) = devices {
+            info.devices = devices;
+        }
+        Ok(info.clone())
+    }
+    
+    /// Role granted by the peer's join token
+    pub fn role(&self) -> PeerRole {
+        self.info.lock().unwrap().role
+    }
+    
+    /// Set the role granted by authentication
+    pub fn set_role(&self, role: PeerRole) {
+        self.info.lock().unwrap().role = role;
+    }
+    
+    /// Whether a GM has muted this peer
+    pub fn is_muted(&self) -> bool {
+        self.muted.load(Ordering::Relaxed)
+    }
+    
+    /// Pause the peer's audio producers and block resuming them, returning
+    /// the paused producer IDs
+    pub async fn mute(&self) -> Result<Vec<String>> {
+        self.muted.store(true, Ordering::Relaxed);
+        
+        let producers: Vec<Producer> = self.producers
+            .iter()
+            .filter(|producer| producer.kind() == MediaKind::Audio)
+            .map(|producer| producer.value().clone())
+            .collect();
+        let mut producer_ids = Vec::with_capacity(producers.len());
+        for producer in producers {
+            producer.pause().await
+                .map_err(|e| MediaSoupError::Producer(e.to_string()))?;
+            producer_ids.push(producer.id().to_string());
+        }
+        
+        Ok(producer_ids)
+    }
+    
+    /// Allow the peer to resume its audio again
+    pub fn unmute(&self) {
+        self.muted.store(false, Ordering::Relaxed);
+    }
+    
+    /// Tell the peer what a GM, or the admin API when `by_user_id` is None,
+    /// did to it, and disconnect it for kicks, bans and closed rooms
+    pub fn moderate(&self, action: ModerationAction, by_user_id: Option<String>, producer_ids: Vec<String>) -> Result<()> {
+        let reason = match action {
+            ModerationAction::Kick => Some(format!("kicked by {}", by_user_id.as_deref().unwrap_or("admin"))),
+            ModerationAction::Ban => Some("banned from room".to_string()),
+            ModerationAction::CloseRoom => Some("room closed".to_string()),
+            ModerationAction::Mute | ModerationAction::Unmute | ModerationAction::StopVideo => None,
+        };
+        let sent = self.send_message(SignalingMessage::notification(
+            "moderated".to_string(),
+            Some(serde_json::to_value(ModeratedNotification {
+                action,
+                by_user_id,
+                producer_ids,
+            })?),
+        ));
+        if let Some(reason) = reason {
+            self.request_disconnect(&reason);
+        }
+        sent
+    }
+    
+    /// Send a message to this peer
+    pub fn send_message(&self, message: SignalingMessage) -> Result<()> {
+        self.send_frame(OutgoingFrame::new(&message)?)
+    }
+    
+    /// Send an already serialized frame to this peer
+    ///
+    /// Lossy notifications are dropped while the outgoing queue is full. Any
+    /// other message hitting a full queue, or a queue that stays full for
+    /// longer than the slow peer timeout, disconnects the peer.
+    pub fn send_frame(&self, frame: OutgoingFrame) -> Result<()> {
+        let lossy = frame.lossy;
+        
+        match self.message_sender.try_send(frame) {
+            Ok(()) => {
+                *self.stalled_since.lock().unwrap() = None;
+                self.high_water_mark.fetch_max(self.queue_depth(), Ordering::Relaxed);
+                Ok(())
+            }
+            Err(TrySendError::Full(frame)) if lossy => {
+                self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+                debug!("Dropped {} notification for slow peer {}", frame.method, self.id);
+                
+                let mut stalled_since = self.stalled_since.lock().unwrap();
+                let since = *stalled_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= self.slow_peer_timeout {
+                    drop(stalled_since);
+                    self.request_disconnect("outgoing queue stalled");
+                }
+                Ok(())
+            }
+            Err(TrySendError::Full(frame)) => {
+                self.dropped_messages.fetch_add(1, Ordering::Relaxed);
+                self.request_disconnect("outgoing queue full");
+                Err(MediaSoupError::QueueFull(format!(
+                    "{} (dropped {})",
+                    self.id, frame.method
+                )))
+            }
+            Err(TrySendError::Closed(_)) => {
+                Err(MediaSoupError::InvalidRequest("Peer disconnected".to_string()))
+            }
+        }
+    }
+    
+    /// Number of messages currently waiting to be written to the socket
+    pub fn queue_depth(&self) -> usize {
+        self.message_sender.max_capacity() - self.message_sender.capacity()
+    }
+    
+    /// Current outgoing queue metrics
+    pub fn queue_stats(&self) -> OutgoingQueueStats {
+        OutgoingQueueStats {
+            depth: self.queue_depth(),
+            capacity: self.message_sender.max_capacity(),
+            high_water_mark: self.high_water_mark.load(Ordering::Relaxed),
+            dropped: self.dropped_messages.load(Ordering::Relaxed),
+        }
+    }
+    
+    /// Tell the client that one of its transports changed ICE or DTLS state
+    pub fn notify_transport_state(
+        &self,
+        transport_id: &str,
+        ice_state: Option<IceState>,
+        dtls_state: Option<DtlsState>,
+    ) -> Result<()> {
+        let notification = SignalingMessage::notification(
+            "transportStateChanged".to_string(),
+            Some(serde_json::to_value(TransportStateChangedNotification {
+                transport_id: transport_id.to_string(),
+                ice_state: ice_state.map(serde_json::to_value).transpose()?,
+                dtls_state: dtls_state.map(serde_json::to_value).transpose()?,
+            })?),
+        );
+        
+        self.send_message(notification)
+    }
+    
+    /// Override the room's last-N and pinned users for this peer
+    pub fn set_last_n(&self, last_n: Option<usize>, pinned_user_ids: Vec<String>) {
+        *self.last_n.lock().unwrap() = last_n;
+        *self.pinned_user_ids.lock().unwrap() = pinned_user_ids.into_iter().collect();
+    }
+    
+    /// Last-N for this peer: its own N, or the room's when it has none
+    pub fn effective_last_n(&self, room_last_n: Option<usize>) -> Option<usize> {
+        (*self.last_n.lock().unwrap()).or(room_last_n)
+    }
+    
+    /// Forget a consumer and its pause state
+    pub fn remove_consumer(&self, consumer_id: &str) {
+        self.consumers.remove(consumer_id);
+        self.client_paused.lock().unwrap().remove(consumer_id);
+        self.last_n_paused.lock().unwrap().remove(consumer_id);
+    }
+    
+    /// Remember that the client started connecting a transport, returning
+    /// false if it already had
+    pub fn mark_connect_requested(&self, transport_id: &str) -> bool {
+        self.connect_requested.lock().unwrap().insert(transport_id.to_string())
+    }
+    
+    /// Whether the client has called `connectTransport` on a transport
+    pub fn connect_requested(&self, transport_id: &str) -> bool {
+        self.connect_requested.lock().unwrap().contains(transport_id)
+    }
+    
+    /// Record a rate or resource limit violation, disconnecting the peer once
+    /// it has made `max_violations` of them
+    pub fn record_violation(&self, error: &MediaSoupError, max_violations: u32) {
+        let violations = self.limit_violations.fetch_add(1, Ordering::Relaxed) + 1;
+        warn!("Peer {} violated limits ({} total): {}", self.id, violations, error);
+        if violations >= max_violations {
+            self.request_disconnect("too many limit violations");
+        }
+    }
+    
+    /// Take a request token and parse an incoming frame
+    ///
+    /// The token is taken before parsing, so malformed frames are rate limited
+    /// too, and they count as violations like requests over the rate. The flag
+    /// is false when the request is over the rate and must be refused.
+    pub fn receive_request(&self, text: &str, max_violations: u32) -> Result<(SignalingMessage, bool)> {
+        let allowed = self.request_limiter.lock().unwrap().try_acquire();
+        let message: SignalingMessage = match serde_json::from_str(text) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = MediaSoupError::from(e);
+                self.record_violation(&error, max_violations);
+                return Err(error);
+            }
+        };
+        if !allowed {
+            let error = MediaSoupError::RateLimited(format!("Too many requests ({})", message.method));
+            self.record_violation(&error, max_violations);
+        }
+        Ok((message, allowed))
+    }
+    
+    /// Check a `broadcast`/`sendToPeer` payload against the size and rate
+    /// limits and wrap it in a `peerMessage` notification from this peer
+    ///
+    /// Spectators are refused, since `fromUserId` would reveal them to players.
+    pub fn relay_message(&self, broadcast: bool, payload: Value, max_size: usize) -> Result<SignalingMessage> {
+        check_can_publish(self)?;
+        let size = serde_json::to_vec(&payload)?.len();
+        if size > max_size {
+            return Err(MediaSoupError::LimitExceeded(format!(
+                "Relay payload of {} bytes exceeds {} bytes",
+                size, max_size
+            )));
+        }
+        if !self.relay_limiter.lock().unwrap().try_acquire() {
+            return Err(MediaSoupError::RateLimited("Too many relayed messages".to_string()));
+        }
+        
+        Ok(SignalingMessage::notification(
+            "peerMessage".to_string(),
+            Some(serde_json::to_value(PeerMessageNotification {
+                from_user_id: self.user_id.clone(),
+                broadcast,
+                payload,
+            })?),
+        ))
+    }
+    
+    /// Ask the connection handling this peer to close
+    ///
+    /// Only the first request is logged; later ones are no-ops.
+    pub fn request_disconnect(&self, reason: &str) {
+        if self.disconnecting.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        warn!("Disconnecting peer {}: {}", self.id, reason);
+        self.disconnect.notify_one();
+    }
+    
+    /// Wait until a disconnect has been requested
+    pub async fn disconnect_requested(&self) {
+        self.disconnect.notified().await;
+    }
+    
+    /// Close all transports, producers, and consumers
+    pub async fn close(&self) -> Result<()> {
+        // Note: In the new API, close methods may be private or work differently
+        // For now, we'll just remove references and let Drop handlers clean up
+        
+        // Clear all collections
+        self.consumers.clear();
+        self.client_paused.lock().unwrap().clear();
+        self.last_n_paused.lock().unwrap().clear();
+        self.producers.clear();
+        self.data_consumers.clear();
+        self.data_producers.clear();
+        self.transports.clear();
+        self.connect_requested.lock().unwrap().clear();
+        
+        Ok(())
+    }
+}
+
+/// Convert a configured listen IP into a mediasoup listen info
+pub fn listen_info(listen_ip: &ListenIp, protocol: Protocol, port: Option<u16>) -> Result<ListenInfo> {
+    let ip: IpAddr = listen_ip.ip.parse()
+        .map_err(|_| MediaSoupError::Config(format!("Invalid listen IP: {}", listen_ip.ip)))?;
+    
+    Ok(ListenInfo {
+        protocol,
+        ip,
+        announced_address: listen_ip.announced_ip.clone(),
+        port, // None lets mediasoup choose
+        port_range: None,
+        flags: None,
+        send_buffer_size: None,
+        recv_buffer_size: None,
+    })
+}
+
+/// Timer that started a transport watchdog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportTimeout {
+    /// Started when the transport is created, for transports never connected
+    Idle,
+    /// Started by the first `connectTransport` or an ICE disconnect
+    Connect,
+}
+
+impl TransportTimeout {
+    /// Reason sent in `transportClosed` when this timer closes a transport
+    pub fn reason(self) -> &'static str {
+        match self {
+            Self::Idle => "idleTimeout",
+            Self::Connect => "connectTimeout",
+        }
+    }
+}
+
+/// Whether a transport watchdog should close a transport when its timer fires
+///
+/// The idle timer closes transports the client never called
+/// `connectTransport` on. It is longer than the connect timer, since
+/// mediasoup-client only connects a transport on its first produce or
+/// consume. The connect timer closes transports that are not ICE and DTLS
+/// connected by then.
+pub fn should_close_unconnected(
+    timeout: TransportTimeout,
+    connect_requested: bool,
+    ice_state: IceState,
+    dtls_state: DtlsState,
+) -> bool {
+    let connected = matches!(ice_state, IceState::Connected | IceState::Completed)
+        && dtls_state == DtlsState::Connected;
+    match timeout {
+        TransportTimeout::Idle => !connect_requested,
+        TransportTimeout::Connect => connect_requested && !connected,
+    }
+}
+
+/// Check that a producer's encodings describe a layout mediasoup can forward
+///
+/// Audio takes a single encoding. Video takes either one encoding, optionally
+/// with an SVC scalability mode, or up to `MAX_SIMULCAST_ENCODINGS` simulcast
+/// encodings that each carry a unique RID or SSRC and only temporal layers.
+pub fn validate_encodings(kind: MediaKind, rtp_parameters: &RtpParameters) -> Result<()> {
+    let encodings = &rtp_parameters.encodings;
+    if encodings.is_empty() {
+        return Err(MediaSoupError::InvalidRequest("At least one encoding is required".to_string()));
+    }
+    
+    if kind == MediaKind::Audio {
+        if encodings.len() > 1 {
+            return Err(MediaSoupError::InvalidRequest("Audio producers take a single encoding".to_string()));
+        }
+        return Ok(());
+    }
+    
+    if encodings.len() == 1 {
+        return Ok(());
+    }
+    
+    if encodings.len() > MAX_SIMULCAST_ENCODINGS {
+        return Err(MediaSoupError::InvalidRequest(format!(
+            "At most {} simulcast encodings are allowed",
+            MAX_SIMULCAST_ENCODINGS
+        )));
+    }
+    
+    let mut rids = HashSet::new();
+    let mut ssrcs = HashSet::new();
+    for encoding in encodings {
+        let unique = match (&encoding.rid, encoding.ssrc) {
+            (Some(rid), _) => rids.insert(rid.clone()),
+            (None, Some(ssrc)) => ssrcs.insert(ssrc),
+            (None, None) => {
+                return Err(MediaSoupError::InvalidRequest("Simulcast encodings need a rid or ssrc".to_string()));
+            }
+        };
+        if !unique {
+            return Err(MediaSoupError::InvalidRequest("Simulcast encodings must have unique rids and ssrcs".to_string()));
+        }
+        
+        if encoding.scalability_mode.spatial_layers().get() > 1 {
+            return Err(MediaSoupError::InvalidRequest(format!(
+                "Simulcast encodings cannot use spatial layers ({:?})",
+                encoding.scalability_mode
+            )));
+        }
+    }
+    
+    if !rids.is_empty() && !ssrcs.is_empty() {
+        return Err(MediaSoupError::InvalidRequest("Simulcast encodings must all use rids or all use ssrcs".to_string()));
+    }
+    
+    Ok(())
+}
+
+/// Refuse a peer consuming a producer or data producer it owns
+pub fn check_not_own(owner_peer_id: &str, peer: &Peer, what: &str) -> Result<()> {
+    if owner_peer_id == peer.id {
+        return Err(MediaSoupError::InvalidRequest(format!("Cannot consume your own {}", what)));
+    }
+    Ok(())
+}
+
+/// Refuse spectators anything that sends to the room
+pub fn check_can_publish(peer: &Peer) -> Result<()> {
+    if peer.role() == PeerRole::Spectator {
+        return Err(MediaSoupError::PublishNotAllowed("Spectators can only receive".to_string()));
+    }
+    Ok(())
+}
+
+/// Refuse a producer beyond the server-wide per-peer limit
+pub fn check_producer_limit(peer: &Peer, max_producers: usize) -> Result<()> {
+    if peer.producers.len() >= max_producers {
+        return Err(MediaSoupError::LimitExceeded(format!("Maximum of {} producers per peer", max_producers)));
+    }
+    Ok(())
+}
+
+/// Refuse moderation requests from peers without the GM role
+pub fn require_gm(peer: &Peer) -> Result<()> {
+    if peer.role() != PeerRole::Gm {
+        return Err(MediaSoupError::Forbidden("GM role required".to_string()));
+    }
+    Ok(())
+}
+
+/// Whether a viewer is told about a peer; spectators are hidden from
+/// everyone but GMs unless `show_spectators` is set
+pub fn is_visible_to(peer: PeerRole, viewer: PeerRole, show_spectators: bool) -> bool {
+    peer != PeerRole::Spectator || show_spectators || viewer == PeerRole::Gm
+}
+
+/// Whether a join is let past the room password; GMs need none
+pub fn password_matches(expected: Option<&str>, role: PeerRole, given: Option<&str>) -> bool {
+    match (expected, given) {
+        (None, _) => true,
+        _ if role == PeerRole::Gm => true,
+        (Some(expected), Some(given)) => constant_time_eq(given.as_bytes(), expected.as_bytes()),
+        (Some(_), None) => false,
+    }
+}
+
+/// Sessions of `user_id` that `sender` may message directly
+///
+/// Sessions hidden from the sender are left out, so `sendToPeer` can't be used
+/// to find out whether a hidden spectator is in the room.
+pub fn relay_targets(
+    peers: impl IntoIterator<Item = Arc<Peer>>,
+    sender: &Peer,
+    user_id: &str,
+    show_spectators: bool,
+) -> Vec<Arc<Peer>> {
+    peers
+        .into_iter()
+        .filter(|peer| peer.user_id == user_id && is_visible_to(peer.role(), sender.role(), show_spectators))
+        .collect()
+}
+
+/// Split a user's waiting sessions into the one to admit and the rest
+///
+/// A user has at most one session in the room, so the session that would have
+/// won had they all joined directly is admitted: the newest under `Replace`,
+/// the oldest under `Reject`.
+pub fn select_admitted(
+    policy: DuplicateSessionPolicy,
+    mut pending: Vec<Arc<Peer>>,
+) -> Option<(Arc<Peer>, Vec<Arc<Peer>>)> {
+    if pending.is_empty() {
+        return None;
+    }
+    pending.sort_by_key(|peer| peer.joined_at());
+    let admitted = match policy {
+        DuplicateSessionPolicy::Replace => pending.pop()?,
+        DuplicateSessionPolicy::Reject => pending.remove(0),
+    };
+    Some((admitted, pending))
+}
+
+/// Spawn a task on `runtime` that runs `handler` on each event sent to the
+/// returned channel, one at a time and in the order they were sent
+///
+/// mediasoup fires observer callbacks from its own thread; spawning a task per
+/// event would let them reach clients out of order.
+pub fn spawn_ordered<T, F, Fut>(runtime: &tokio::runtime::Handle, mut handler: F) -> mpsc::UnboundedSender<T>
+where
+    T: Send + 'static,
+    F: FnMut(T) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    runtime.spawn(async move {
+        while let Some(event) = receiver.recv().await {
+            handler(event).await;
+        }
+    });
+    sender
+}
+
+/// Pick the peers whose video is forwarded to `peer_id` under last-N
+///
+/// `speaker_order` lists peer IDs by most recent dominant speaker first. Only
+/// peers in `video_publishers` are ranked, so peers without a camera don't
+/// take up slots.
+pub fn select_last_n(
+    speaker_order: &[String],
+    video_publishers: &HashSet<String>,
+    peer_id: &str,
+    last_n: usize,
+) -> HashSet<String> {
+    speaker_order
+        .iter()
+        .filter(|id| id.as_str() != peer_id && video_publishers.contains(id.as_str()))
+        .take(last_n)
+        .cloned()
+        .collect()
+}
+
+/// Room-wide index entry for a producer
+#[derive(Debug, Clone)]
+pub struct ProducerEntry {
+    pub peer_id: String,
+    pub user_id: String,
+    pub transport_id: String,
+    pub producer: Producer,
+    pub app_data: Option<Value>,
+}
+
+/// Room-wide index entry for an SCTP data producer
+#[derive(Debug, Clone)]
+pub struct DataProducerEntry {
+    pub peer_id: String,
+    pub user_id: String,
+    pub transport_id: String,
+    pub data_producer: DataProducer,
+    pub app_data: Option<Value>,
+}
+
+/// Room-wide index entry for an SCTP data consumer
+#[derive(Debug, Clone)]
+pub struct DataConsumerEntry {
+    pub peer_id: String,
+    pub transport_id: String,
+    pub data_producer_id: String,
+}
+
+/// Limits on what a room sends to clients over receive transports
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutgoingBitratePolicy {
+    /// Cap for any single receive transport
+    pub max_per_transport: Option<u32>,
+    /// Total for the room, split across consumers
+    pub room_budget: Option<u32>,
+}
+
+impl OutgoingBitratePolicy {
+    /// Split the room budget across receive transports carrying `consumer_counts` consumers
+    ///
+    /// Shares are proportional to the consumer counts and capped at
+    /// `max_per_transport`. A transport whose share would fall below
+    /// mediasoup's minimum gets the minimum and the others split what is left.
+    /// Only when the budget can't cover the minimum for every transport does
+    /// the total exceed it. Returns `None` without a room budget.
+    pub fn split(&self, consumer_counts: &[usize]) -> Option<Vec<u32>> {
+        let budget = u64::from(self.room_budget?);
+        let min = u64::from(MIN_OUTGOING_BITRATE);
+        let mut floored = vec![false; consumer_counts.len()];
+        let mut shares = vec![0u64; consumer_counts.len()];
+        
+        loop {
+            let floored_total = min * floored.iter().filter(|floored| **floored).count() as u64;
+            let remaining = budget.saturating_sub(floored_total);
+            let weight: u64 = consumer_counts.iter()
+                .zip(&floored)
+                .filter(|(_, floored)| !**floored)
+                .map(|(count, _)| *count as u64)
+                .sum();
+            
+            let mut changed = false;
+            for (i, count) in consumer_counts.iter().enumerate() {
+                if floored[i] {
+                    continue;
+                }
+                shares[i] = (remaining * *count as u64).checked_div(weight).unwrap_or(0);
+                if shares[i] < min {
+                    floored[i] = true;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        
+        Some(
+            shares.into_iter()
+                .zip(floored)
+                .map(|(share, floored)| {
+                    let share = if floored { MIN_OUTGOING_BITRATE } else { u32::try_from(share).unwrap_or(u32::MAX) };
+                    self.max_per_transport
+                        .map_or(share, |max| share.min(max))
+                        .max(MIN_OUTGOING_BITRATE)
+                })
+                .collect(),
+        )
+    }
+    
+    /// Outgoing bitrates that changed since the last rebalance, by transport ID
+    ///
+    /// Transports with consumers get their `split` of the budget. Transports
+    /// that had a share in `applied` but lost their last consumer go back to
+    /// `max_per_transport`, or to 0 (unlimited) without one, so consumers
+    /// attached later don't start under-provisioned. They are always listed,
+    /// so the caller can stop tracking them.
+    pub fn rebalance(
+        &self,
+        consumer_counts: &HashMap<String, usize>,
+        applied: &HashMap<String, u32>,
+    ) -> Vec<(String, u32)> {
+        let mut transport_ids: Vec<&String> = consumer_counts.keys().collect();
+        transport_ids.sort();
+        let counts: Vec<usize> = transport_ids.iter().map(|id| consumer_counts[*id]).collect();
+        let shares = self.split(&counts).unwrap_or_default();
+        
+        let idle = applied
+            .keys()
+            .filter(|id| !consumer_counts.contains_key(*id))
+            .map(|id| (id.clone(), self.max_per_transport.unwrap_or(0)));
+        transport_ids
+            .into_iter()
+            .cloned()
+            .zip(shares)
+            .filter(|(id, bitrate)| applied.get(id) != Some(bitrate))
+            .chain(idle)
+            .collect()
+    }
+}
+
+/// Clamp a client's `setMaxIncomingBitrate` request to the server cap
+///
+/// Clients may lower their limit but never raise it past the cap. 0 means
+/// unlimited to mediasoup, so it is replaced by the cap as well.
+pub fn clamp_incoming_bitrate(requested: u32, max: Option<u32>) -> u32 {
+    match max {
+        Some(max) if requested == 0 || requested > max => max,
+        _ => requested,
+    }
+}
+
+/// Room-wide index entry for a consumer
+#[derive(Debug, Clone)]
+pub struct ConsumerEntry {
+    pub peer_id: String,
+    pub transport_id: String,
+    pub producer_id: String,
+}
+
+/// Banned user IDs per room ID
+///
+/// Held by the server rather than the room, so bans outlive a closed room and
+/// can be set before a room exists.
+#[derive(Debug, Default)]
+pub struct BanList {
+    rooms: DashMap<String, HashSet<String>>,
+}
+
+impl BanList {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+    
+    /// Ban a user from a room, returning whether they were not already banned
+    pub fn ban(&self, room_id: &str, user_id: &str) -> bool {
+        self.rooms.entry(room_id.to_string()).or_default().insert(user_id.to_string())
+    }
+    
+    /// Lift a ban, returning whether the user was banned
+    pub fn unban(&self, room_id: &str, user_id: &str) -> bool {
+        let Some(mut user_ids) = self.rooms.get_mut(room_id) else {
+            return false;
+        };
+        let removed = user_ids.remove(user_id);
+        let empty = user_ids.is_empty();
+        drop(user_ids);
+        if empty {
+            self.rooms.remove_if(room_id, |_, user_ids| user_ids.is_empty());
+        }
+        removed
+    }
+    
+    /// Whether a user is banned from a room
+    pub fn is_banned(&self, room_id: &str, user_id: &str) -> bool {
+        self.rooms.get(room_id).is_some_and(|user_ids| user_ids.contains(user_id))
+    }
+    
+    /// User IDs banned from a room, sorted
+    pub fn banned_user_ids(&self, room_id: &str) -> Vec<String> {
+        let mut user_ids: Vec<String> = self.rooms
+            .get(room_id)
+            .map(|user_ids| user_ids.iter().cloned().collect())
+            .unwrap_or_default();
+        user_ids.sort();
+        user_ids
+    }
+}
+
+/// Room that manages peers and MediaSoup router
+#[derive(Debug)]
+pub struct Room {
+    pub id: String,
+    pub router: Router,
+    pub webrtc_server: Option<WebRtcServer>,
+    pub peers: Arc<DashMap<String, Arc<Peer>>>,
+    /// Producer ID -> owning peer and producer
+    pub producers: DashMap<String, ProducerEntry>,
+    /// Consumer ID -> consuming peer
+    pub consumers: DashMap<String, ConsumerEntry>,
+    /// Data producer ID -> owning peer and data producer
+    pub data_producers: DashMap<String, DataProducerEntry>,
+    /// Data consumer ID -> consuming peer
+    pub data_consumers: DashMap<String, DataConsumerEntry>,
+    pub outgoing_bitrate: OutgoingBitratePolicy,
+    pub active_speaker_observer: ActiveSpeakerObserver,
+    pub audio_level_observer: AudioLevelObserver,
+    /// Peer IDs by most recent dominant speaker, then join order
+    speaker_order: Mutex<Vec<String>>,
+    last_n: Mutex<Option<usize>>,
+    pinned_user_ids: Mutex<HashSet<String>>,
+    /// Serializes last-N passes so pause/resume calls don't interleave
+    last_n_lock: tokio::sync::Mutex<()>,
+    /// Serializes the capacity check and insert of joining peers
+    admission_lock: tokio::sync::Mutex<()>,
+    /// Serializes the publish checks and insert of new producers
+    publish_lock: tokio::sync::Mutex<()>,
+    /// Serializes outgoing bitrate rebalances so overlapping requests share one pass
+    rebalance_lock: tokio::sync::Mutex<()>,
+    rebalance_requested: AtomicU64,
+    rebalance_applied: AtomicU64,
+    /// Transport ID -> outgoing bitrate last applied by a rebalance
+    applied_outgoing_bitrate: DashMap<String, u32>,
+    /// Keyframe requests per producer ID
+    key_frame_throttle: KeyedThrottle,
+    /// Outgoing messages dropped by peers that have left
+    departed_dropped_messages: AtomicU64,
+    /// User ID -> peer ID of the user's session
+    sessions: DashMap<String, String>,
+    duplicate_sessions: DuplicateSessionPolicy,
+    bans: Arc<BanList>,
+    /// Peer ID -> peers waiting for a GM to admit them
+    lobby: DashMap<String, Arc<Peer>>,
+    lobby_enabled: bool,
+    password: Option<String>,
+    max_peers: Option<usize>,
+    max_video_publishers: Option<usize>,
+    video_publish_roles: Vec<PeerRole>,
+    show_spectators: bool,
+}
+
+impl Room {
+    /// Create a new room with a MediaSoup router
+    ///
+    /// Transports are created on `webrtc_server` when given, so they share its ports.
+    pub async fn new(
+        id: String,
+        worker: &Worker,
+        webrtc_server: Option<WebRtcServer>,
+        outgoing_bitrate: OutgoingBitratePolicy,
+        config: &RoomConfig,
+        bans: Arc<BanList>,
+    ) -> Result<Arc<Self>> {
+        let router = worker
+            .create_router(RouterOptions::new(Self::media_codecs()))
+            .await?;
+        
+        let mut observer_options = ActiveSpeakerObserverOptions::default();
+        observer_options.interval = config.active_speaker_interval_ms;
+        let active_speaker_observer = router.create_active_speaker_observer(observer_options).await?;
+        
+        let mut observer_options = AudioLevelObserverOptions::default();
+        observer_options.max_entries = NonZeroU16::new(MAX_AUDIO_LEVEL_ENTRIES).unwrap();
+        observer_options.threshold = config.audio_level_threshold;
+        observer_options.interval = config.audio_level_interval_ms;
+        let audio_level_observer = router.create_audio_level_observer(observer_options).await?;
+        
+        info!("Created room {} with router {}", id, router.id());
+        
+        let room = Arc::new(Self {
+            id,
+            router,
+            webrtc_server,
+            peers: Arc::new(DashMap::new()),
+            producers: DashMap::new(),
+            consumers: DashMap::new(),
+            data_producers: DashMap::new(),
+            data_consumers: DashMap::new(),
+            outgoing_bitrate,
+            active_speaker_observer,
+            audio_level_observer,
+            speaker_order: Mutex::new(Vec::new()),
+            last_n: Mutex::new(config.last_n),
+            pinned_user_ids: Mutex::new(HashSet::new()),
+            last_n_lock: tokio::sync::Mutex::new(()),
+            admission_lock: tokio::sync::Mutex::new(()),
+            publish_lock: tokio::sync::Mutex::new(()),
+            rebalance_lock: tokio::sync::Mutex::new(()),
+            rebalance_requested: AtomicU64::new(0),
+            rebalance_applied: AtomicU64::new(0),
+            applied_outgoing_bitrate: DashMap::new(),
+            key_frame_throttle: KeyedThrottle::new(Duration::from_millis(config.key_frame_request_interval_ms)),
+            departed_dropped_messages: AtomicU64::new(0),
+            sessions: DashMap::new(),
+            duplicate_sessions: config.duplicate_sessions,
+            bans,
+            lobby: DashMap::new(),
+            lobby_enabled: config.lobby,
+            password: config.password.clone(),
+            max_peers: config.max_peers,
+            max_video_publishers: config.max_video_publishers,
+            video_publish_roles: config.video_publish_roles.clone(),
+            show_spectators: config.show_spectators,
+        });
+        
+        // mediasoup reports observer events from its own thread; each kind is
+        // handled in order so clients see the latest state last
+        let runtime = tokio::runtime::Handle::current();
+        let dominant_speakers = spawn_ordered(&runtime, {
+            let room = Arc::downgrade(&room);
+            move |producer_id: String| {
+                let room = room.upgrade();
+                async move {
+                    if let Some(room) = room {
+                        room.handle_dominant_speaker(&producer_id).await;
+                    }
+                }
+            }
+        });
+        
+        room.active_speaker_observer.on_dominant_speaker(move |dominant_speaker| {
+            let _ = dominant_speakers.send(dominant_speaker.producer.id().to_string());
+        }).detach();
+        
+        // Volumes and silence share one queue so the final empty `audioLevels`
+        // can't overtake the levels before it
+        let audio_levels = spawn_ordered(&runtime, {
+            let room = Arc::downgrade(&room);
+            move |volumes: Vec<(String, i8)>| {
+                let room = room.upgrade();
+                async move {
+                    if let Some(room) = room {
+                        room.broadcast_audio_levels(volumes).await;
+                    }
+                }
+            }
+        });
+        
+        room.audio_level_observer.on_volumes({
+            let audio_levels = audio_levels.clone();
+            move |volumes| {
+                let volumes: Vec<(String, i8)> = volumes
+                    .iter()
+                    .map(|volume| (volume.producer.id().to_string(), volume.volume))
+                    .collect();
+                let _ = audio_levels.send(volumes);
+            }
+        }).detach();
+        
+        room.audio_level_observer.on_silence(move || {
+            let _ = audio_levels.send(Vec::new());
+        }).detach();
+        
+        Ok(room)
+    }
+    
+    /// Add a peer to the room
+    ///
+    /// Banned users are refused. With the lobby enabled, peers other than GMs
+    /// wait without transports until a GM admits them.
+    pub async fn add_peer(&self, peer: Arc<Peer>) -> Result<()> {
+        if self.is_banned(&peer.user_id) {
+            return Err(MediaSoupError::Forbidden("Banned from this room".to_string()));
+        }
+        self.check_capacity(&peer)?;
+        
+        if self.lobby_enabled && peer.role() != PeerRole::Gm {
+            self.lobby.insert(peer.id.clone(), peer.clone());
+            info!("Peer {} waiting in lobby of room {}", peer.id, self.id);
+            
+            peer.send_message(SignalingMessage::notification("joinPending".to_string(), None))?;
+            self.send_to_gms(SignalingMessage::notification(
+                "joinRequest".to_string(),
+                Some(serde_json::to_value(peer.info())?),
+            ))?;
+            return Ok(());
+        }
+        
+        self.enter(peer).await
+    }
+    
+    /// Check the password given at join; GMs need none
+    pub fn check_password(&self, peer: &Peer, password: Option<&str>) -> Result<()> {
+        if !password_matches(self.password.as_deref(), peer.role(), password) {
+            return Err(MediaSoupError::Unauthorized("Invalid room password".to_string()));
+        }
+        Ok(())
+    }
+    
+    /// Refuse a peer when the room is at its maximum size
+    ///
+    /// GMs always fit, and a session replacing the user's existing one does
+    /// not grow the room.
+    pub fn check_capacity(&self, peer: &Peer) -> Result<()> {
+        let Some(max_peers) = self.max_peers else { return Ok(()) };
+        let replacing = self.duplicate_sessions == DuplicateSessionPolicy::Replace
+            && self.sessions.contains_key(&peer.user_id);
+        if peer.role() == PeerRole::Gm || replacing {
+            return Ok(());
+        }
+        
+        let members = self.peers.iter().filter(|member| member.role() != PeerRole::Gm).count();
+        if members >= max_peers {
+            return Err(MediaSoupError::RoomFull(format!("Room {} is limited to {} peers", self.id, max_peers)));
+        }
+        Ok(())
+    }
+    
+    /// Check the room's publish rules before a peer produces media
+    pub fn check_publish(&self, peer: &Peer, kind: MediaKind) -> Result<()> {
+        check_can_publish(peer)?;
+        if kind == MediaKind::Video && !self.video_publish_roles.contains(&peer.role()) {
+            return Err(MediaSoupError::PublishNotAllowed(format!(
+                "Role {:?} may not publish video",
+                peer.role()
+            )));
+        }
+        
+        if let (MediaKind::Video, Some(max_publishers)) = (kind, self.max_video_publishers) {
+            let publishers: HashSet<String> = self.producers
+                .iter()
+                .filter(|entry| entry.producer.kind() == MediaKind::Video)
+                .map(|entry| entry.peer_id.clone())
+                .collect();
+            if !publishers.contains(&peer.id) && publishers.len() >= max_publishers {
+                return Err(MediaSoupError::PublishLimit(format!(
+                    "Room {} allows {} video publishers",
+                    self.id, max_publishers
+                )));
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// Whether a peer is waiting in the lobby
+    pub fn is_pending(&self, peer_id: &str) -> bool {
+        self.lobby.contains_key(peer_id)
+    }
+    
+    /// Peers waiting in the lobby
+    pub fn list_join_requests(&self) -> Vec<PeerInfo> {
+        self.lobby.iter().map(|peer| peer.info()).collect()
+    }
+    
+    /// Let a user into the room, returning how many sessions were admitted
+    ///
+    /// Only one of the user's waiting sessions is admitted (see
+    /// `select_admitted`) and the others are turned away. If the room refuses
+    /// the admitted session, every session goes back to the lobby.
+    pub async fn admit(&self, user_id: &str) -> Result<usize> {
+        let Some((peer, others)) = select_admitted(self.duplicate_sessions, self.take_pending(user_id)) else {
+            return Ok(0);
+        };
+        if let Err(e) = self.enter(peer.clone()).await {
+            for peer in std::iter::once(peer).chain(others) {
+                self.lobby.insert(peer.id.clone(), peer);
+            }
+            return Err(e);
+        }
+        info!("Admitted peer {} to room {}", peer.id, self.id);
+        peer.send_message(SignalingMessage::notification("joinAccepted".to_string(), None))?;
+        
+        let (method, reason) = match self.duplicate_sessions {
+            DuplicateSessionPolicy::Replace => ("sessionReplaced", "replaced by a new session"),
+            DuplicateSessionPolicy::Reject => ("joinDenied", "already connected from another session"),
+        };
+        for other in others {
+            if let Err(e) = other.send_message(SignalingMessage::notification(method.to_string(), None)) {
+                warn!("Failed to notify turned away peer {}: {}", other.id, e);
+            }
+            other.request_disconnect(reason);
+        }
+        Ok(1)
+    }
+    
+    /// Turn away a user's waiting sessions, returning how many were denied
+    pub fn deny(&self, user_id: &str) -> usize {
+        let pending = self.take_pending(user_id);
+        for peer in &pending {
+            if let Err(e) = peer.send_message(SignalingMessage::notification("joinDenied".to_string(), None)) {
+                warn!("Failed to notify denied peer {}: {}", peer.id, e);
+            }
+            peer.request_disconnect("denied by GM");
+        }
+        pending.len()
+    }
+    
+    fn take_pending(&self, user_id: &str) -> Vec<Arc<Peer>> {
+        let peer_ids: Vec<String> = self.lobby
+            .iter()
+            .filter(|peer| peer.user_id == user_id)
+            .map(|peer| peer.key().clone())
+            .collect();
+        peer_ids
+            .into_iter()
+            .filter_map(|peer_id| self.lobby.remove(&peer_id).map(|(_, peer)| peer))
+            .collect()
+    }
+    
+    /// Send a message to every GM in the room
+    fn send_to_gms(&self, message: SignalingMessage) -> Result<()> {
+        let frame = OutgoingFrame::new(&message)?;
+        for peer in self.peers.iter().filter(|peer| peer.role() == PeerRole::Gm) {
+            if let Err(e) = peer.send_frame(frame.clone()) {
+                warn!("Failed to send message to peer {}: {}", peer.id, e);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Make a peer a member of the room and announce it
+    ///
+    /// A user already connected to the room either has the old session
+    /// replaced or the new one refused, depending on the duplicate session
+    /// policy.
+    async fn enter(&self, peer: Arc<Peer>) -> Result<()> {
+        let admitting = self.admission_lock.lock().await;
+        self.check_capacity(&peer)?;
+        
+        let replaced = match self.sessions.entry(peer.user_id.clone()) {
+            Entry::Occupied(mut entry) => match self.duplicate_sessions {
+                DuplicateSessionPolicy::Reject => {
+                    return Err(MediaSoupError::Forbidden("Already connected from another session".to_string()));
+                }
+                DuplicateSessionPolicy::Replace => Some(entry.insert(peer.id.clone())),
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(peer.id.clone());
+                None
+            }
+        };
+        if let Some(old_peer_id) = replaced {
+            if let Some(old_peer) = self.get_peer(&old_peer_id) {
+                let notification = SignalingMessage::notification("sessionReplaced".to_string(), None);
+                if let Err(e) = old_peer.send_message(notification) {
+                    warn!("Failed to notify replaced peer {}: {}", old_peer_id, e);
+                }
+                old_peer.request_disconnect("replaced by a new session");
+            }
+            self.remove_peer(&old_peer_id).await?;
+        }
+        
+        let peer_id = peer.id.clone();
+        self.peers.insert(peer_id.clone(), peer.clone());
+        self.speaker_order.lock().unwrap().push(peer_id.clone());
+        drop(admitting);
+        
+        info!("Added peer {} to room {}", peer_id, self.id);
+        
+        let notification = SignalingMessage::notification(
+            "peerJoined".to_string(),
+            Some(serde_json::to_value(peer.info())?),
+        );
+        
+        self.announce_presence(&peer, notification)
+    }
+    
+    /// Remove a peer from the room
+    pub async fn remove_peer(&self, peer_id: &str) -> Result<()> {
+        if let Some((_, peer)) = self.lobby.remove(peer_id) {
+            info!("Peer {} left the lobby of room {}", peer_id, self.id);
+            return self.send_to_gms(SignalingMessage::notification(
+                "joinRequestCancelled".to_string(),
+                Some(serde_json::to_value(JoinRequestCancelledNotification {
+                    user_id: peer.user_id.clone(),
+                })?),
+            ));
+        }
+        
+        if let Some((_, peer)) = self.peers.remove(peer_id) {
+            self.departed_dropped_messages.fetch_add(peer.queue_stats().dropped, Ordering::Relaxed);
+            self.sessions.remove_if(&peer.user_id, |_, session_peer_id| session_peer_id == peer_id);
+            
+            // Close the peer's producers and notify other peers
+            let producer_ids: Vec<String> = self.producers
+                .iter()
+                .filter(|entry| entry.peer_id == peer_id)
+                .map(|entry| entry.key().clone())
+                .collect();
+            for producer_id in producer_ids {
+                self.close_producer(&producer_id).await?;
+            }
+            
+            self.consumers.retain(|_, entry| entry.peer_id != peer_id);
+            self.rebalance_outgoing_bitrate().await;
+            
+            let data_producer_ids: Vec<String> = self.data_producers
+                .iter()
+                .filter(|entry| entry.peer_id == peer_id)
+                .map(|entry| entry.key().clone())
+                .collect();
+            for data_producer_id in data_producer_ids {
+                self.close_data_producer(&data_producer_id).await?;
+            }
+            
+            self.data_consumers.retain(|_, entry| entry.peer_id != peer_id);
+            
+            self.speaker_order.lock().unwrap().retain(|id| id != peer_id);
+            self.apply_last_n().await;
+            
+            // Close peer's resources
+            peer.close().await?;
+            
+            info!("Removed peer {} from room {}", peer_id, self.id);
+            
+            let notification = SignalingMessage::notification(
+                "peerLeft".to_string(),
+                Some(serde_json::to_value(PeerLeftNotification {
+                    user_id: peer.user_id.clone(),
+                })?),
+            );
+            self.announce_presence(&peer, notification)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Close a producer, its consumers, and notify other peers
+    pub async fn close_producer(&self, producer_id: &str) -> Result<()> {
+        let (_, entry) = self.producers.remove(producer_id)
+            .ok_or_else(|| MediaSoupError::ProducerNotFound(producer_id.to_string()))?;
+        
+        if let Some(owner) = self.get_peer(&entry.peer_id) {
+            owner.producers.remove(producer_id);
+        }
+        
+        let consumers: Vec<(String, String)> = self.consumers
+            .iter()
+            .filter(|consumer| consumer.producer_id == producer_id)
+            .map(|consumer| (consumer.key().clone(), consumer.peer_id.clone()))
+            .collect();
+        for (consumer_id, consumer_peer_id) in consumers {
+            self.consumers.remove(&consumer_id);
+            if let Some(consumer_peer) = self.get_peer(&consumer_peer_id) {
+                consumer_peer.remove_consumer(&consumer_id);
+            }
+        }
+        
+        self.key_frame_throttle.remove(producer_id);
+        self.rebalance_outgoing_bitrate().await;
+        if entry.producer.kind() == MediaKind::Video {
+            self.apply_last_n().await;
+        }
+        
+        debug!("Closed producer {} of peer {} in room {}", producer_id, entry.peer_id, self.id);
+        
+        let notification = SignalingMessage::notification(
+            "producerClosed".to_string(),
+            Some(serde_json::to_value(ProducerClosedNotification {
+                producer_id: producer_id.to_string(),
+            })?),
+        );
+        
+        self.broadcast_to_others(&entry.peer_id, notification).await
+    }
+    
+    /// Close a data producer, its data consumers, and notify other peers
+    pub async fn close_data_producer(&self, data_producer_id: &str) -> Result<()> {
+        let (_, entry) = self.data_producers.remove(data_producer_id)
+            .ok_or_else(|| MediaSoupError::DataProducerNotFound(data_producer_id.to_string()))?;
+        
+        if let Some(owner) = self.get_peer(&entry.peer_id) {
+            owner.data_producers.remove(data_producer_id);
+        }
+        
+        let data_consumers: Vec<(String, String)> = self.data_consumers
+            .iter()
+            .filter(|data_consumer| data_consumer.data_producer_id == data_producer_id)
+            .map(|data_consumer| (data_consumer.key().clone(), data_consumer.peer_id.clone()))
+            .collect();
+        for (data_consumer_id, data_consumer_peer_id) in data_consumers {
+            self.data_consumers.remove(&data_consumer_id);
+            if let Some(data_consumer_peer) = self.get_peer(&data_consumer_peer_id) {
+                data_consumer_peer.data_consumers.remove(&data_consumer_id);
+            }
+        }
+        
+        debug!("Closed data producer {} of peer {} in room {}", data_producer_id, entry.peer_id, self.id);
+        
+        let notification = SignalingMessage::notification(
+            "dataProducerClosed".to_string(),
+            Some(serde_json::to_value(DataProducerClosedNotification {
+                data_producer_id: data_producer_id.to_string(),
+            })?),
+        );
+        
+        self.broadcast_to_others(&entry.peer_id, notification).await
+    }
+    
+    /// Close a peer's transport with its producers and consumers
+    ///
+    /// The owner is told why with `transportClosed`; other peers get
+    /// `producerClosed` for each producer that went away.
+    pub async fn close_transport(&self, peer_id: &str, transport_id: &str, reason: &str) -> Result<()> {
+        let peer = self.get_peer(peer_id)
+            .ok_or_else(|| MediaSoupError::PeerNotFound(peer_id.to_string()))?;
+        
+        let Some((_, transport)) = peer.transports.remove(transport_id) else {
+            return Ok(());
+        };
+        peer.connect_requested.lock().unwrap().remove(transport_id);
+        
+        let producer_ids: Vec<String> = self.producers
+            .iter()
+            .filter(|producer| producer.peer_id == peer_id && producer.transport_id == transport_id)
+            .map(|producer| producer.key().clone())
+            .collect();
+        for producer_id in producer_ids {
+            if let Err(e) = self.close_producer(&producer_id).await {
+                warn!("Failed to close producer {}: {}", producer_id, e);
+            }
+        }
+        
+        self.consumers.retain(|consumer_id, consumer| {
+            let owned = consumer.peer_id == peer_id && consumer.transport_id == transport_id;
+            if owned {
+                peer.remove_consumer(consumer_id);
+            }
+            !owned
+        });
+        self.rebalance_outgoing_bitrate().await;
+        
+        let data_producer_ids: Vec<String> = self.data_producers
+            .iter()
+            .filter(|entry| entry.peer_id == peer_id && entry.transport_id == transport_id)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for data_producer_id in data_producer_ids {
+            if let Err(e) = self.close_data_producer(&data_producer_id).await {
+                warn!("Failed to close data producer {}: {}", data_producer_id, e);
+            }
         }
+        
+        self.data_consumers.retain(|data_consumer_id, entry| {
+            let owned = entry.peer_id == peer_id && entry.transport_id == transport_id;
+            if owned {
+                peer.data_consumers.remove(data_consumer_id);
+            }
+            !owned
+        });
+        
+        drop(transport);
+        
+        info!("Closed transport {} of peer {} in room {}: {}", transport_id, peer_id, self.id, reason);
+        
+        peer.send_message(SignalingMessage::notification(
+            "transportClosed".to_string(),
+            Some(serde_json::to_value(TransportClosedNotification {
+                transport_id: transport_id.to_string(),
+                reason: reason.to_string(),
+            })?),
+        ))
     }
     
-    /// Send a message to this peer
-    pub fn send_message(&self, message: SignalingMessage) -> Result<()> {
-        self.message_sender
-            .send(message)
-            .map_err(|_| MediaSoupError::InvalidRequest("Peer disconnected".to_string()))?;
-        Ok(())
+    /// Split the room's outgoing budget across receive transports
+    ///
+    /// See `OutgoingBitratePolicy::split`. Calls made while a pass is running
+    /// are coalesced into the next pass, and only transports whose share
+    /// changed are sent to the worker.
+    pub async fn rebalance_outgoing_bitrate(&self) {
+        if self.outgoing_bitrate.room_budget.is_none() {
+            return;
+        }
+        
+        let request = self.rebalance_requested.fetch_add(1, Ordering::AcqRel) + 1;
+        let _rebalance = self.rebalance_lock.lock().await;
+        if self.rebalance_applied.load(Ordering::Acquire) >= request {
+            return;
+        }
+        let covered = self.rebalance_requested.load(Ordering::Acquire);
+        
+        let mut consumer_counts: HashMap<String, usize> = HashMap::new();
+        for consumer in self.consumers.iter() {
+            *consumer_counts.entry(consumer.transport_id.clone()).or_default() += 1;
+        }
+        let applied: HashMap<String, u32> = self.applied_outgoing_bitrate
+            .iter()
+            .map(|entry| (entry.key().clone(), *entry.value()))
+            .collect();
+        
+        for (transport_id, bitrate) in self.outgoing_bitrate.rebalance(&consumer_counts, &applied) {
+            let Some(transport) = self.peers
+                .iter()
+                .find_map(|peer| peer.transports.get(&transport_id).map(|transport| transport.clone()))
+            else {
+                self.applied_outgoing_bitrate.remove(&transport_id);
+                continue;
+            };
+            
+            match transport.set_max_outgoing_bitrate(bitrate).await {
+                // Idle transports are back at the cap and need no further tracking
+                Ok(()) if !consumer_counts.contains_key(&transport_id) => {
+                    self.applied_outgoing_bitrate.remove(&transport_id);
+                }
+                Ok(()) => {
+                    self.applied_outgoing_bitrate.insert(transport_id, bitrate);
+                }
+                Err(e) => warn!("Failed to set outgoing bitrate of transport {}: {}", transport_id, e),
+            }
+        }
+        
+        self.rebalance_applied.store(covered, Ordering::Release);
     }
     
-    /// Close all transports, producers, and consumers
-    pub async fn close(&self) -> Result<()> {
-        // Note: In the new API, close methods may be private or work differently
-        // For now, we'll just remove references and let Drop handlers clean up
+    /// Replace the room-wide last-N and pinned users
+    pub fn set_last_n(&self, last_n: Option<usize>, pinned_user_ids: Vec<String>) {
+        *self.last_n.lock().unwrap() = last_n;
+        *self.pinned_user_ids.lock().unwrap() = pinned_user_ids.into_iter().collect();
+    }
+    
+    /// Announce the dominant speaker and move them to the front of the speaker order
+    async fn handle_dominant_speaker(&self, producer_id: &str) {
+        let Some((peer_id, user_id)) = self.producers
+            .get(producer_id)
+            .map(|entry| (entry.peer_id.clone(), entry.user_id.clone()))
+        else {
+            return;
+        };
         
-        // Clear all collections
-        self.consumers.clear();
-        self.producers.clear();
-        self.transports.clear();
+        let notification = ActiveSpeakerNotification {
+            user_id,
+            producer_id: producer_id.to_string(),
+        };
+        match serde_json::to_value(notification) {
+            Ok(data) => {
+                let message = SignalingMessage::notification("activeSpeaker".to_string(), Some(data));
+                if let Err(e) = self.broadcast_to_all(message).await {
+                    warn!("Failed to broadcast active speaker in room {}: {}", self.id, e);
+                }
+            }
+            Err(e) => warn!("Failed to serialize active speaker: {}", e),
+        }
         
-        Ok(())
+        {
+            let mut speaker_order = self.speaker_order.lock().unwrap();
+            if speaker_order.first() == Some(&peer_id) {
+                return;
+            }
+            speaker_order.retain(|id| id != &peer_id);
+            speaker_order.insert(0, peer_id.clone());
+        }
+        
+        debug!("Dominant speaker in room {} is now peer {}", self.id, peer_id);
+        
+        self.apply_last_n().await;
     }
-}
-
-/// Room that manages peers and MediaSoup router
-#[derive(Debug)]
-pub struct Room {
-    pub id: String,
-    pub router: Router,
-    pub peers: Arc<DashMap<String, Arc<Peer>>>,
-}
-
-impl Room {
-    /// Create a new room with a MediaSoup router
-    pub async fn new(id: String, worker: &Worker) -> Result<Self> {
-        let router = worker
-            .create_router(RouterOptions::new(Self::media_codecs()))
-            .await?;
-            
-        info!("Created room {} with router {}", id, router.id());
+    
+    /// Send producer volumes, tagged with their users, to every peer
+    async fn broadcast_audio_levels(&self, volumes: Vec<(String, i8)>) {
+        let levels = volumes
+            .into_iter()
+            .filter_map(|(producer_id, volume)| {
+                let user_id = self.producers.get(&producer_id)?.user_id.clone();
+                Some(AudioLevel { user_id, producer_id, volume })
+            })
+            .collect();
         
-        Ok(Self {
-            id,
-            router,
-            peers: Arc::new(DashMap::new()),
-        })
+        let message = match serde_json::to_value(AudioLevelsNotification { levels }) {
+            Ok(data) => SignalingMessage::notification("audioLevels".to_string(), Some(data)),
+            Err(e) => {
+                warn!("Failed to serialize audio levels: {}", e);
+                return;
+            }
+        };
+        
+        if let Err(e) = self.broadcast_to_all(message).await {
+            warn!("Failed to broadcast audio levels in room {}: {}", self.id, e);
+        }
     }
     
-    /// Add a peer to the room
-    pub async fn add_peer(&self, peer: Arc<Peer>) -> Result<()> {
-        let peer_id = peer.id.clone();
-        self.peers.insert(peer_id.clone(), peer.clone());
+    /// Pause and resume video consumers of every peer to match last-N
+    pub async fn apply_last_n(&self) {
+        let peers: Vec<Arc<Peer>> = self.peers.iter().map(|peer| peer.clone()).collect();
+        for peer in peers {
+            self.apply_last_n_to_peer(&peer).await;
+        }
+    }
+    
+    /// Pause video consumers from peers outside this peer's last-N and pinned
+    /// users, and resume the ones last-N paused earlier once they are back in
+    async fn apply_last_n_to_peer(&self, peer: &Peer) {
+        let _guard = self.last_n_lock.lock().await;
         
-        info!("Added peer {} to room {}", peer_id, self.id);
+        let last_n = peer.effective_last_n(*self.last_n.lock().unwrap());
+        let forwarded = last_n.map(|last_n| {
+            let video_publishers: HashSet<String> = self.producers
+                .iter()
+                .filter(|entry| entry.producer.kind() == MediaKind::Video)
+                .map(|entry| entry.peer_id.clone())
+                .collect();
+            let speaker_order = self.speaker_order.lock().unwrap();
+            let mut forwarded = select_last_n(&speaker_order, &video_publishers, &peer.id, last_n);
+            let room_pinned = self.pinned_user_ids.lock().unwrap();
+            let peer_pinned = peer.pinned_user_ids.lock().unwrap();
+            for other in self.peers.iter() {
+                if room_pinned.contains(&other.user_id) || peer_pinned.contains(&other.user_id) {
+                    forwarded.insert(other.id.clone());
+                }
+            }
+            forwarded
+        });
         
-        // Notify existing peers about new producer when this peer creates one
-        // This will be handled in the produce method
+        let consumers: Vec<(Consumer, String)> = peer.consumers
+            .iter()
+            .filter(|consumer| consumer.kind() == MediaKind::Video)
+            .filter_map(|consumer| {
+                let producer_id = self.consumers.get(consumer.key())?.producer_id.clone();
+                let owner = self.producers.get(&producer_id)?.peer_id.clone();
+                Some((consumer.clone(), owner))
+            })
+            .collect();
         
-        Ok(())
+        for (consumer, owner) in consumers {
+            let consumer_id = consumer.id().to_string();
+            let forward = forwarded.as_ref().is_none_or(|forwarded| forwarded.contains(&owner));
+            
+            let changed = if forward {
+                peer.last_n_paused.lock().unwrap().remove(&consumer_id)
+            } else {
+                peer.last_n_paused.lock().unwrap().insert(consumer_id.clone())
+            };
+            if !changed {
+                continue;
+            }
+            
+            if let Err(e) = self.sync_consumer_pause(peer, &consumer).await {
+                warn!("Failed to update consumer {}: {}", consumer_id, e);
+                continue;
+            }
+            
+            let method = if forward { "consumerResumed" } else { "consumerPaused" };
+            
+            let notification = ConsumerPausedNotification {
+                consumer_id,
+                reason: "lastN".to_string(),
+            };
+            let result = serde_json::to_value(notification)
+                .map_err(MediaSoupError::from)
+                .and_then(|data| peer.send_message(SignalingMessage::notification(method.to_string(), Some(data))));
+            if let Err(e) = result {
+                warn!("Failed to notify peer {} of last-N change: {}", peer.id, e);
+            }
+        }
     }
     
-    /// Remove a peer from the room
-    pub async fn remove_peer(&self, peer_id: &str) -> Result<()> {
-        if let Some((_, peer)) = self.peers.remove(peer_id) {
-            // Close peer's resources
-            peer.close().await?;
+    /// Close the transport after `delay` if `should_close_unconnected` says so
+    fn spawn_connect_watchdog(
+        self: &Arc<Self>,
+        peer_id: String,
+        transport_id: String,
+        timeout: TransportTimeout,
+        delay: Duration,
+    ) {
+        let room = Arc::downgrade(self);
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
             
-            // Notify other peers about closed producers
-            for producer in peer.producers.iter() {
-                let notification = SignalingMessage::notification(
-                    "producerClosed".to_string(),
-                    Some(serde_json::to_value(ProducerClosedNotification {
-                        producer_id: producer.id().to_string(),
-                    })?),
-                );
-                
-                self.broadcast_to_others(&peer.id, notification).await?;
-            }
+            let Some(room) = room.upgrade() else { return };
+            let Some(peer) = room.get_peer(&peer_id) else { return };
+            let close = match peer.transports.get(&transport_id) {
+                Some(transport) => should_close_unconnected(
+                    timeout,
+                    peer.connect_requested(&transport_id),
+                    transport.ice_state(),
+                    transport.dtls_state(),
+                ),
+                None => return,
+            };
             
-            info!("Removed peer {} from room {}", peer_id, self.id);
+            if close {
+                if let Err(e) = room.close_transport(&peer_id, &transport_id, timeout.reason()).await {
+                    warn!("Failed to close transport {}: {}", transport_id, e);
+                }
+            }
+        });
+    }
+    
+    /// Resume a peer's consumer on the client's request
+    ///
+    /// Returns whether the consumer is still paused because last-N holds it back.
+    pub async fn resume_consumer(&self, peer: &Peer, consumer_id: &str) -> Result<bool> {
+        let consumer = peer.consumers.get(consumer_id)
+            .ok_or_else(|| MediaSoupError::ConsumerNotFound(consumer_id.to_string()))?
+            .clone();
+        
+        peer.client_paused.lock().unwrap().remove(consumer_id);
+        self.sync_consumer_pause(peer, &consumer).await
+    }
+    
+    /// Pause or resume a consumer so it only runs when neither the client
+    /// nor last-N holds it back, returning whether it is paused
+    ///
+    /// Video consumers that start running get a keyframe request so the
+    /// client doesn't wait for the next periodic keyframe. Within the
+    /// producer's throttle interval the request is sent once the interval
+    /// ends, so a last-N change resuming many consumers of one producer sends
+    /// it at most two requests and none of them is left without a keyframe.
+    async fn sync_consumer_pause(&self, peer: &Peer, consumer: &Consumer) -> Result<bool> {
+        let consumer_id = consumer.id().to_string();
+        let paused = peer.client_paused.lock().unwrap().contains(&consumer_id)
+            || peer.last_n_paused.lock().unwrap().contains(&consumer_id);
+        
+        if paused && !consumer.paused() {
+            consumer.pause().await
+                .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+        } else if !paused && consumer.paused() {
+            consumer.resume().await
+                .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+            if consumer.kind() == MediaKind::Video {
+                self.request_resume_key_frame(consumer).await?;
+            }
+        }
+        
+        Ok(paused)
+    }
+    
+    /// Ask the producer behind a resumed consumer for a keyframe, now or when
+    /// the throttle interval ends
+    async fn request_resume_key_frame(&self, consumer: &Consumer) -> Result<()> {
+        match self.key_frame_throttle.acquire_or_defer(&consumer.producer_id().to_string()) {
+            Deferral::Now => {
+                consumer.request_key_frame().await
+                    .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+            }
+            Deferral::After(delay) => {
+                let consumer = consumer.clone();
+                tokio::spawn(async move {
+                    tokio::time::sleep(delay).await;
+                    if let Err(e) = consumer.request_key_frame().await {
+                        debug!("Deferred keyframe request for consumer {} failed: {}", consumer.id(), e);
+                    }
+                });
+            }
+            Deferral::Scheduled => {}
+        }
+        Ok(())
+    }
+    
+    /// Ask the producer behind a consumer for a keyframe
+    ///
+    /// Requests for the same producer are coalesced within the throttle
+    /// interval since the resulting keyframe reaches every consumer. Returns
+    /// whether a request was sent.
+    pub async fn request_key_frame(&self, consumer: &Consumer) -> Result<bool> {
+        if !self.key_frame_throttle.try_acquire(&consumer.producer_id().to_string()) {
+            return Ok(false);
         }
         
+        consumer.request_key_frame().await
+            .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+        Ok(true)
+    }
+    
+    /// List all data producers in the room
+    pub fn list_data_producers(&self) -> Vec<DataProducerEntry> {
+        self.data_producers.iter().map(|entry| entry.clone()).collect()
+    }
+    
+    /// Look up a producer anywhere in the room
+    pub fn get_producer(&self, producer_id: &str) -> Option<ProducerEntry> {
+        self.producers.get(producer_id).map(|entry| entry.clone())
+    }
+    
+    /// List all producers in the room
+    pub fn list_producers(&self) -> Vec<ProducerEntry> {
+        self.producers.iter().map(|entry| entry.clone()).collect()
+    }
+    
+    /// Details of every other peer in the room that `viewer` may see
+    pub fn list_peer_info(&self, viewer: &Peer) -> Vec<PeerInfo> {
+        self.peers
+            .iter()
+            .filter(|peer| peer.id != viewer.id && is_visible_to(peer.role(), viewer.role(), self.show_spectators))
+            .map(|peer| peer.info())
+            .collect()
+    }
+    
+    /// Send a presence notification about `peer` to the other peers that may see it
+    pub fn announce_presence(&self, peer: &Peer, message: SignalingMessage) -> Result<()> {
+        let frame = OutgoingFrame::new(&message)?;
+        for viewer in self.peers.iter() {
+            if viewer.id != peer.id && is_visible_to(peer.role(), viewer.role(), self.show_spectators) {
+                if let Err(e) = viewer.send_frame(frame.clone()) {
+                    warn!("Failed to send message to peer {}: {}", viewer.id, e);
+                }
+            }
+        }
         Ok(())
     }
     
@@ -123,11 +1769,31 @@ impl Room {
         self.peers.get(peer_id).map(|entry| entry.clone())
     }
     
+    /// Outgoing queue totals for the room
+    pub fn queue_metrics(&self) -> RoomQueueMetrics {
+        let mut metrics = RoomQueueMetrics {
+            dropped: self.departed_dropped_messages.load(Ordering::Relaxed),
+            ..Default::default()
+        };
+        for peer in self.peers.iter() {
+            let stats = peer.queue_stats();
+            metrics.peers += 1;
+            metrics.depth += stats.depth;
+            metrics.max_depth = metrics.max_depth.max(stats.depth);
+            metrics.dropped += stats.dropped;
+        }
+        metrics
+    }
+    
     /// Broadcast a message to all peers except the sender
+    ///
+    /// The message is serialized once and the resulting frame is shared by
+    /// every recipient.
     pub async fn broadcast_to_others(&self, sender_id: &str, message: SignalingMessage) -> Result<()> {
+        let frame = OutgoingFrame::new(&message)?;
         for peer in self.peers.iter() {
             if peer.id != sender_id {
-                if let Err(e) = peer.send_message(message.clone()) {
+                if let Err(e) = peer.send_frame(frame.clone()) {
                     warn!("Failed to send message to peer {}: {}", peer.id, e);
                 }
             }
@@ -135,10 +1801,85 @@ impl Room {
         Ok(())
     }
     
+    /// Whether a user is banned from the room
+    pub fn is_banned(&self, user_id: &str) -> bool {
+        self.bans.is_banned(&self.id, user_id)
+    }
+    
+    /// Banned user IDs, sorted
+    pub fn banned_user_ids(&self) -> Vec<String> {
+        self.bans.banned_user_ids(&self.id)
+    }
+    
+    /// Ban a user and disconnect their sessions
+    ///
+    /// `by_user_id` is the GM who issued the ban, or None for the admin API.
+    pub fn ban_user(&self, user_id: &str, by_user_id: Option<String>) -> Result<()> {
+        self.bans.ban(&self.id, user_id);
+        info!("Banned user {} from room {}", user_id, self.id);
+        
+        for peer in self.peers_for_user(user_id).into_iter().chain(self.take_pending(user_id)) {
+            if let Err(e) = peer.moderate(ModerationAction::Ban, by_user_id.clone(), Vec::new()) {
+                warn!("Failed to notify banned peer {}: {}", peer.id, e);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Lift a ban, returning whether the user was banned
+    pub fn unban_user(&self, user_id: &str) -> bool {
+        self.bans.unban(&self.id, user_id)
+    }
+    
+    /// Ask every member and waiting peer to disconnect
+    pub fn disconnect_all(&self, reason: &str) {
+        for peer in self.peers.iter().chain(self.lobby.iter()) {
+            peer.request_disconnect(reason);
+        }
+    }
+    
+    /// Connected sessions of a user
+    pub fn peers_for_user(&self, user_id: &str) -> Vec<Arc<Peer>> {
+        self.peers
+            .iter()
+            .filter(|peer| peer.user_id == user_id)
+            .map(|peer| peer.value().clone())
+            .collect()
+    }
+    
+    /// Close a peer's producers of one kind, returning their IDs
+    pub async fn close_producers_of_kind(&self, peer_id: &str, kind: MediaKind) -> Result<Vec<String>> {
+        let producer_ids: Vec<String> = self.producers
+            .iter()
+            .filter(|entry| entry.peer_id == peer_id && entry.producer.kind() == kind)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for producer_id in &producer_ids {
+            self.close_producer(producer_id).await?;
+        }
+        Ok(producer_ids)
+    }
+    
+    /// Send a message to every session of a user visible to `sender`,
+    /// returning how many received it
+    pub async fn send_to_user(&self, sender: &Peer, user_id: &str, message: SignalingMessage) -> Result<usize> {
+        let frame = OutgoingFrame::new(&message)?;
+        let peers = self.peers.iter().map(|peer| peer.value().clone());
+        let mut delivered = 0;
+        for peer in relay_targets(peers, sender, user_id, self.show_spectators) {
+            match peer.send_frame(frame.clone()) {
+                Ok(()) => delivered += 1,
+                Err(e) => warn!("Failed to send message to peer {}: {}", peer.id, e),
+            }
+        }
+        Ok(delivered)
+    }
+    
     /// Broadcast a message to all peers
     pub async fn broadcast_to_all(&self, message: SignalingMessage) -> Result<()> {
+        let frame = OutgoingFrame::new(&message)?;
         for peer in self.peers.iter() {
-            if let Err(e) = peer.send_message(message.clone()) {
+            if let Err(e) = peer.send_frame(frame.clone()) {
                 warn!("Failed to send message to peer {}: {}", peer.id, e);
             }
         }
@@ -146,58 +1887,163 @@ impl Room {
     }
     
     /// Create a WebRTC transport for a peer
+    ///
+    /// The transport is closed if the client doesn't call `connectTransport`
+    /// within `idle_timeout`, if it stays ICE-disconnected for longer than
+    /// `connect_timeout`, or if its DTLS session fails. See `connect_transport`
+    /// for transports that fail to connect.
+    #[allow(clippy::too_many_arguments)]
     pub async fn create_webrtc_transport(
-        &self,
+        self: &Arc<Self>,
         peer_id: &str,
         listen_ips: Vec<ListenIp>,
         enable_udp: bool,
         enable_tcp: bool,
         prefer_udp: bool,
         enable_sctp: bool,
+        idle_timeout: Duration,
+        connect_timeout: Duration,
     ) -> Result<WebRtcTransport> {
         let peer = self.get_peer(peer_id)
             .ok_or_else(|| MediaSoupError::PeerNotFound(peer_id.to_string()))?;
         
-        // Convert custom ListenIp structs to mediasoup ListenInfo structs
-        let listen_infos: Vec<ListenInfo> = listen_ips
-            .into_iter()
-            .map(|listen_ip| ListenInfo {
-                protocol: Protocol::Udp, // Default to UDP
-                ip: listen_ip.ip.parse().unwrap_or_else(|_| "0.0.0.0".parse().unwrap()),
-                announced_address: listen_ip.announced_ip,
-                port: None, // Let mediasoup choose
-                port_range: None,
-                flags: None,
-                send_buffer_size: None,
-                recv_buffer_size: None,
-            })
-            .collect();
-
+        // Share the worker's WebRtcServer ports if there is one, otherwise
+        // listen on every configured IP with each enabled protocol
+        let mut options = match &self.webrtc_server {
+            Some(webrtc_server) => WebRtcTransportOptions::new_with_server(webrtc_server.clone()),
+            None => {
+                let protocols: Vec<Protocol> = [(enable_udp, Protocol::Udp), (enable_tcp, Protocol::Tcp)]
+                    .into_iter()
+                    .filter_map(|(enabled, protocol)| enabled.then_some(protocol))
+                    .collect();
+                
+                let mut listen_infos = Vec::new();
+                for listen_ip in &listen_ips {
+                    for protocol in &protocols {
+                        listen_infos.push(listen_info(listen_ip, *protocol, None)?);
+                    }
+                }
+                
+                let listen_infos = WebRtcTransportListenInfos::try_from(listen_infos).map_err(|_| {
+                    MediaSoupError::Config("No listen IPs or protocols enabled for WebRTC transports".to_string())
+                })?;
+                
+                WebRtcTransportOptions::new(listen_infos)
+            }
+        };
+        options.enable_udp = enable_udp;
+        options.enable_tcp = enable_tcp;
+        options.prefer_udp = enable_udp && prefer_udp;
+        options.prefer_tcp = enable_tcp && !prefer_udp;
+        options.enable_sctp = enable_sctp;
+        
         let transport = self
             .router
-            .create_webrtc_transport(WebRtcTransportOptions::new(WebRtcTransportListenInfos::new(
-                listen_infos.into_iter().next().unwrap_or(ListenInfo {
-                    protocol: Protocol::Udp,
-                    ip: "0.0.0.0".parse().unwrap(),
-                    announced_address: None,
-                    port: None,
-                    port_range: None,
-                    flags: None,
-                    send_buffer_size: None,
-                    recv_buffer_size: None,
-                }),
-            )))
+            .create_webrtc_transport(options)
             .await?;
         
         let transport_id = transport.id().to_string();
+        
+        // Forward state changes so the client knows when to restart ICE. The
+        // handlers hold weak references and live as long as the transport.
+        // mediasoup calls them from its own thread, so cleanup is spawned on
+        // the runtime that created the transport.
+        let runtime = tokio::runtime::Handle::current();
+        
+        transport.on_ice_state_change({
+            let room = Arc::downgrade(self);
+            let peer = Arc::downgrade(&peer);
+            let transport_id = transport_id.clone();
+            let runtime = runtime.clone();
+            move |ice_state| {
+                let Some(peer) = peer.upgrade() else { return };
+                debug!("Transport {} ICE state changed to {:?}", transport_id, ice_state);
+                if let Err(e) = peer.notify_transport_state(&transport_id, Some(ice_state), None) {
+                    warn!("Failed to notify peer {} of ICE state: {}", peer.id, e);
+                }
+                
+                // Give the client the same window to restart ICE
+                if ice_state == IceState::Disconnected {
+                    if let Some(room) = room.upgrade() {
+                        let _guard = runtime.enter();
+                        room.spawn_connect_watchdog(
+                            peer.id.clone(),
+                            transport_id.clone(),
+                            TransportTimeout::Connect,
+                            connect_timeout,
+                        );
+                    }
+                }
+            }
+        }).detach();
+        
+        transport.on_dtls_state_change({
+            let room = Arc::downgrade(self);
+            let peer = Arc::downgrade(&peer);
+            let transport_id = transport_id.clone();
+            move |dtls_state| {
+                let Some(peer) = peer.upgrade() else { return };
+                debug!("Transport {} DTLS state changed to {:?}", transport_id, dtls_state);
+                if let Err(e) = peer.notify_transport_state(&transport_id, None, Some(dtls_state)) {
+                    warn!("Failed to notify peer {} of DTLS state: {}", peer.id, e);
+                }
+                
+                if matches!(dtls_state, DtlsState::Failed | DtlsState::Closed) {
+                    let Some(room) = room.upgrade() else { return };
+                    let peer_id = peer.id.clone();
+                    let transport_id = transport_id.clone();
+                    let reason = format!("dtls{:?}", dtls_state);
+                    runtime.spawn(async move {
+                        if let Err(e) = room.close_transport(&peer_id, &transport_id, &reason).await {
+                            warn!("Failed to close transport {}: {}", transport_id, e);
+                        }
+                    });
+                }
+            }
+        }).detach();
+        
         peer.transports.insert(transport_id.clone(), transport.clone());
+        self.spawn_connect_watchdog(peer.id.clone(), transport_id.clone(), TransportTimeout::Idle, idle_timeout);
         
         debug!("Created WebRTC transport {} for peer {}", transport_id, peer_id);
         
         Ok(transport)
     }
     
+    /// Connect a peer's transport with the client's DTLS parameters
+    ///
+    /// The transport is closed if it is not ICE and DTLS connected within
+    /// `connect_timeout` of the first connect.
+    pub async fn connect_transport(
+        self: &Arc<Self>,
+        peer: &Peer,
+        transport_id: &str,
+        dtls_parameters: DtlsParameters,
+        connect_timeout: Duration,
+    ) -> Result<()> {
+        let transport = peer.transports.get(transport_id)
+            .ok_or_else(|| MediaSoupError::TransportNotFound(transport_id.to_string()))?
+            .clone();
+        
+        transport.connect(WebRtcTransportRemoteParameters { dtls_parameters }).await
+            .map_err(|e| MediaSoupError::Transport(e.to_string()))?;
+        
+        if peer.mark_connect_requested(transport_id) {
+            self.spawn_connect_watchdog(
+                peer.id.clone(),
+                transport_id.to_string(),
+                TransportTimeout::Connect,
+                connect_timeout,
+            );
+        }
+        Ok(())
+    }
+    
     /// Handle producer creation and notify other peers
+    ///
+    /// The room's publish rules and `max_producers` per peer are checked here,
+    /// under the same lock as the insert, so concurrent produce requests can't
+    /// overshoot them.
     pub async fn create_producer(
         &self,
         peer_id: &str,
@@ -205,23 +2051,54 @@ impl Room {
         kind: MediaKind,
         rtp_parameters: RtpParameters,
         app_data: Option<Value>,
+        max_producers: usize,
     ) -> Result<Producer> {
         let peer = self.get_peer(peer_id)
             .ok_or_else(|| MediaSoupError::PeerNotFound(peer_id.to_string()))?;
         
+        let publishing = self.publish_lock.lock().await;
+        self.check_publish(&peer, kind)?;
+        check_producer_limit(&peer, max_producers)?;
+        
         let transport = peer.transports.get(transport_id)
             .ok_or_else(|| MediaSoupError::TransportNotFound(transport_id.to_string()))?;
         
         let mut options = ProducerOptions::new(kind, rtp_parameters);
-        if let Some(app_data) = app_data {
-            options.app_data = AppData::new(app_data);
+        if let Some(app_data) = &app_data {
+            options.app_data = AppData::new(app_data.clone());
         }
         
         let producer = transport.produce(options).await
             .map_err(|e| MediaSoupError::Producer(e.to_string()))?;
+        drop(transport);
         
+        // Index the producer first so observer events for it can be resolved
         let producer_id = producer.id().to_string();
         peer.producers.insert(producer_id.clone(), producer.clone());
+        self.producers.insert(producer_id.clone(), ProducerEntry {
+            peer_id: peer.id.clone(),
+            user_id: peer.user_id.clone(),
+            transport_id: transport_id.to_string(),
+            producer: producer.clone(),
+            app_data: app_data.clone(),
+        });
+        
+        if kind == MediaKind::Audio {
+            let observed = async {
+                self.active_speaker_observer
+                    .add_producer(RtpObserverAddProducerOptions::new(producer.id()))
+                    .await?;
+                self.audio_level_observer
+                    .add_producer(RtpObserverAddProducerOptions::new(producer.id()))
+                    .await
+            };
+            if let Err(e) = observed.await {
+                peer.producers.remove(&producer_id);
+                self.producers.remove(&producer_id);
+                return Err(e.into());
+            }
+        }
+        drop(publishing);
         
         info!("Created producer {} for peer {} in room {}", producer_id, peer_id, self.id);
         
@@ -232,11 +2109,17 @@ impl Room {
                 id: producer_id,
                 user_id: peer.user_id.clone(),
                 kind: format!("{:?}", kind),
+                app_data,
             })?),
         );
         
         self.broadcast_to_others(&peer.id, notification).await?;
         
+        // Publishing video puts the peer in the running for last-N slots
+        if kind == MediaKind::Video {
+            self.apply_last_n().await;
+        }
+        
         Ok(producer)
     }
     
@@ -254,36 +2137,163 @@ impl Room {
         let transport = peer.transports.get(transport_id)
             .ok_or_else(|| MediaSoupError::TransportNotFound(transport_id.to_string()))?;
         
-        // Find the producer in any peer
-        let mut producer_option = None;
-        for other_peer in self.peers.iter() {
-            if let Some(producer) = other_peer.producers.get(producer_id) {
-                producer_option = Some(producer.clone());
-                break;
-            }
-        }
-        
-        let producer = producer_option
+        let entry = self.get_producer(producer_id)
             .ok_or_else(|| MediaSoupError::ProducerNotFound(producer_id.to_string()))?;
+        check_not_own(&entry.peer_id, &peer, "producer")?;
+        let producer = entry.producer;
         
         // Check if router can consume this producer
         if !self.router.can_consume(&producer.id(), &rtp_capabilities) {
             return Err(MediaSoupError::Consumer("Cannot consume producer".to_string()));
         }
         
+        // Start paused so no media (and no keyframe) is lost before the
+        // client has set up its track and sends resumeConsumer
+        let mut options = ConsumerOptions::new(producer.id(), rtp_capabilities);
+        options.paused = true;
+        
         let consumer = transport
-            .consume(ConsumerOptions::new(producer.id(), rtp_capabilities))
+            .consume(options)
             .await
             .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
         
         let consumer_id = consumer.id().to_string();
+        peer.client_paused.lock().unwrap().insert(consumer_id.clone());
+        
+        // Tell the viewer which simulcast/SVC layer it is actually receiving
+        consumer.on_layers_change({
+            let peer = Arc::downgrade(&peer);
+            let consumer_id = consumer_id.clone();
+            move |layers| {
+                let Some(peer) = peer.upgrade() else { return };
+                let notification = ConsumerLayersChangedNotification {
+                    consumer_id: consumer_id.clone(),
+                    spatial_layer: layers.map(|layers| layers.spatial_layer),
+                    temporal_layer: layers.and_then(|layers| layers.temporal_layer),
+                };
+                let result = serde_json::to_value(notification)
+                    .map_err(MediaSoupError::from)
+                    .and_then(|data| {
+                        peer.send_message(SignalingMessage::notification(
+                            "consumerLayersChanged".to_string(),
+                            Some(data),
+                        ))
+                    });
+                if let Err(e) = result {
+                    warn!("Failed to notify peer {} of consumer layers: {}", peer.id, e);
+                }
+            }
+        }).detach();
+        
         peer.consumers.insert(consumer_id.clone(), consumer.clone());
+        self.consumers.insert(consumer_id.clone(), ConsumerEntry {
+            peer_id: peer.id.clone(),
+            transport_id: transport_id.to_string(),
+            producer_id: producer_id.to_string(),
+        });
+        
+        self.rebalance_outgoing_bitrate().await;
+        self.apply_last_n_to_peer(&peer).await;
         
         debug!("Created consumer {} for peer {} in room {}", consumer_id, peer_id, self.id);
         
         Ok(consumer)
     }
     
+    /// Create an SCTP data producer and announce it to other peers
+    pub async fn create_data_producer(
+        &self,
+        peer_id: &str,
+        transport_id: &str,
+        sctp_stream_parameters: SctpStreamParameters,
+        label: String,
+        protocol: String,
+        app_data: Option<Value>,
+    ) -> Result<DataProducer> {
+        let peer = self.get_peer(peer_id)
+            .ok_or_else(|| MediaSoupError::PeerNotFound(peer_id.to_string()))?;
+        
+        let transport = peer.transports.get(transport_id)
+            .ok_or_else(|| MediaSoupError::TransportNotFound(transport_id.to_string()))?
+            .clone();
+        
+        let mut options = DataProducerOptions::new_sctp(sctp_stream_parameters);
+        options.label = label.clone();
+        options.protocol = protocol.clone();
+        if let Some(app_data) = &app_data {
+            options.app_data = AppData::new(app_data.clone());
+        }
+        
+        let data_producer = transport.produce_data(options).await
+            .map_err(|e| MediaSoupError::Producer(e.to_string()))?;
+        
+        let data_producer_id = data_producer.id().to_string();
+        peer.data_producers.insert(data_producer_id.clone(), data_producer.clone());
+        self.data_producers.insert(data_producer_id.clone(), DataProducerEntry {
+            peer_id: peer.id.clone(),
+            user_id: peer.user_id.clone(),
+            transport_id: transport_id.to_string(),
+            data_producer: data_producer.clone(),
+            app_data: app_data.clone(),
+        });
+        
+        info!("Created data producer {} for peer {} in room {}", data_producer_id, peer_id, self.id);
+        
+        let notification = SignalingMessage::notification(
+            "newDataProducer".to_string(),
+            Some(serde_json::to_value(NewDataProducerNotification {
+                id: data_producer_id,
+                user_id: peer.user_id.clone(),
+                label,
+                protocol,
+                app_data,
+            })?),
+        );
+        
+        self.broadcast_to_others(&peer.id, notification).await?;
+        
+        Ok(data_producer)
+    }
+    
+    /// Create a data consumer for a peer to receive another peer's data producer
+    pub async fn create_data_consumer(
+        &self,
+        peer_id: &str,
+        transport_id: &str,
+        data_producer_id: &str,
+    ) -> Result<DataConsumer> {
+        let peer = self.get_peer(peer_id)
+            .ok_or_else(|| MediaSoupError::PeerNotFound(peer_id.to_string()))?;
+        
+        let transport = peer.transports.get(transport_id)
+            .ok_or_else(|| MediaSoupError::TransportNotFound(transport_id.to_string()))?
+            .clone();
+        
+        let data_producer = {
+            let entry = self.data_producers.get(data_producer_id)
+                .ok_or_else(|| MediaSoupError::DataProducerNotFound(data_producer_id.to_string()))?;
+            check_not_own(&entry.peer_id, &peer, "data producer")?;
+            entry.data_producer.clone()
+        };
+        
+        let data_consumer = transport
+            .consume_data(DataConsumerOptions::new_sctp(data_producer.id()))
+            .await
+            .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+        
+        let data_consumer_id = data_consumer.id().to_string();
+        peer.data_consumers.insert(data_consumer_id.clone(), data_consumer.clone());
+        self.data_consumers.insert(data_consumer_id.clone(), DataConsumerEntry {
+            peer_id: peer.id.clone(),
+            transport_id: transport_id.to_string(),
+            data_producer_id: data_producer_id.to_string(),
+        });
+        
+        debug!("Created data consumer {} for peer {} in room {}", data_consumer_id, peer_id, self.id);
+        
+        Ok(data_consumer)
+    }
+    
     /// Get RTP capabilities of the router
     pub fn get_rtp_capabilities(&self) -> &RtpCapabilitiesFinalized {
         self.router.rtp_capabilities()
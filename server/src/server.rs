@@ -1,7 +1,12 @@
-use crate::config::Config;
+use crate::admin;
+use crate::auth::{query_param, verify_token};
+use crate::config::{Config, ListenIp, WebRtcServerConfig};
 use crate::error::{MediaSoupError, Result};
-use crate::room::{Peer, Room};
+use crate::limits::{complete_handshake, is_origin_allowed, websocket_config, ConnectionGuard, ConnectionRejection, ConnectionTracker, RateLimiter};
+use crate::room::{check_can_publish, clamp_incoming_bitrate, listen_info, require_gm, validate_encodings, BanList, OutgoingBitratePolicy, Peer, Room};
 use crate::signaling::*;
+use crate::tls::CertificateStore;
+use crate::turn::issue_credentials;
 use dashmap::DashMap;
 use futures_util::{SinkExt, StreamExt};
 use mediasoup::prelude::*;
@@ -10,28 +15,50 @@ use mediasoup::worker::WorkerSettings;
 use serde_json::Value;
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::mpsc;
-use tokio_tungstenite::{accept_async, tungstenite::Message, WebSocketStream};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::{header::ORIGIN, StatusCode};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::{Error, Message};
+use tokio_tungstenite::{accept_hdr_async_with_config, WebSocketStream};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+/// How often outgoing queue metrics are logged
+const QUEUE_METRICS_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long queued messages may take to flush when a connection closes
+const CLOSE_DRAIN_TIMEOUT: Duration = Duration::from_secs(1);
+
 /// Main MediaSoup server
 pub struct MediaSoupServer {
     config: Config,
     worker_manager: CustomWorkerManager,
     rooms: Arc<DashMap<String, Arc<Room>>>,
+    /// Kept apart from the rooms so closing a room does not lift its bans
+    bans: Arc<BanList>,
+    connections: Arc<ConnectionTracker>,
 }
 
 impl MediaSoupServer {
     /// Create a new MediaSoup server
     pub async fn new(config: Config) -> Result<Self> {
         let worker_manager = CustomWorkerManager::new(&config).await?;
+        let connections = ConnectionTracker::new(
+            config.signaling.max_connections,
+            config.signaling.max_connections_per_ip,
+        );
         
         Ok(Self {
             config,
             worker_manager,
             rooms: Arc::new(DashMap::new()),
+            bans: BanList::new(),
+            connections,
         })
     }
     
@@ -40,14 +67,48 @@ impl MediaSoupServer {
         let listener = TcpListener::bind(&self.config.listen_addr).await
             .map_err(|e| MediaSoupError::Config(format!("Failed to bind to {}: {}", self.config.listen_addr, e)))?;
         
-        info!("WebSocket server listening on {}", self.config.listen_addr);
+        let certificates = match &self.config.tls {
+            Some(tls) => {
+                let store = Arc::new(CertificateStore::load(tls.clone())?);
+                store.spawn_reload_task();
+                Some(store)
+            }
+            None => None,
+        };
+        
+        info!(
+            "WebSocket server listening on {}{}",
+            self.config.listen_addr,
+            if certificates.is_some() { " (TLS)" } else { "" }
+        );
+        
+        match (self.config.http_addr, &self.config.admin) {
+            (Some(addr), Some(admin_config)) => {
+                admin::spawn(addr, admin_config, self.rooms.clone(), self.bans.clone())?;
+            }
+            (Some(_), None) => warn!("MEDIASOUP_ADMIN_TOKEN is not set; admin API disabled"),
+            _ => {}
+        }
         
         let server = Arc::new(self);
+        server.spawn_queue_metrics_task();
+        let handshake_timeout = Duration::from_millis(server.config.signaling.handshake_timeout_ms);
         
         while let Ok((stream, addr)) = listener.accept().await {
             let server_clone = server.clone();
+            let acceptor = certificates.as_ref().map(|store| store.acceptor());
+            let admission = server.connections.try_acquire(addr.ip());
             tokio::spawn(async move {
-                if let Err(e) = server_clone.handle_connection(stream, addr).await {
+                let result = match acceptor {
+                    Some(acceptor) => match complete_handshake(handshake_timeout, acceptor.accept(stream)).await {
+                        Some(Ok(stream)) => server_clone.handle_connection(stream, addr, admission).await,
+                        Some(Err(e)) => Err(MediaSoupError::Tls(format!("Handshake failed: {}", e))),
+                        None => Err(MediaSoupError::Tls("Handshake timed out".to_string())),
+                    },
+                    None => server_clone.handle_connection(stream, addr, admission).await,
+                };
+                
+                if let Err(e) = result {
                     error!("Error handling connection from {}: {}", addr, e);
                 }
             });
@@ -56,47 +117,170 @@ impl MediaSoupServer {
         Ok(())
     }
     
+    /// Periodically log outgoing queue depth for every room
+    fn spawn_queue_metrics_task(&self) {
+        let rooms = self.rooms.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(QUEUE_METRICS_INTERVAL);
+            loop {
+                interval.tick().await;
+                for room in rooms.iter() {
+                    let metrics = room.queue_metrics();
+                    if metrics.peers == 0 {
+                        continue;
+                    }
+                    
+                    debug!(
+                        "Room {} outgoing queues: peers={} total_depth={} max_depth={} dropped={}",
+                        room.id, metrics.peers, metrics.depth, metrics.max_depth, metrics.dropped
+                    );
+                }
+            }
+        });
+    }
+    
     /// Handle a new WebSocket connection
-    async fn handle_connection(&self, stream: TcpStream, addr: SocketAddr) -> Result<()> {
-        info!("New connection from {}", addr);
+    ///
+    /// Upgrades over the connection limits, from an origin outside the
+    /// allowlist, or without a valid join token when authentication is
+    /// configured are answered with an HTTP error status.
+    async fn handle_connection<S>(
+        &self,
+        stream: S,
+        addr: SocketAddr,
+        admission: std::result::Result<ConnectionGuard, ConnectionRejection>,
+    ) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        info!("New connection from {} ({} open)", addr, self.connections.total());
+        
+        let rejection = admission.as_ref().err().copied();
+        let allowed_origins = &self.config.signaling.allowed_origins;
+        let auth = self.config.auth.as_ref();
+        let mut claims = None;
+        let mut password = None;
+        #[allow(clippy::result_large_err)] // Error type is fixed by the handshake callback signature
+        let check_upgrade = |request: &Request, response: Response| {
+            if let Some(rejection) = rejection {
+                return Err(Self::reject_upgrade(rejection.status(), rejection.reason()));
+            }
+            
+            let origin = request.headers().get(ORIGIN).and_then(|value| value.to_str().ok());
+            if !is_origin_allowed(allowed_origins, origin) {
+                return Err(Self::reject_upgrade(StatusCode::FORBIDDEN, "Origin not allowed"));
+            }
+            
+            password = query_param(request.uri().query(), "password");
+            if let Some(auth) = auth {
+                let token = query_param(request.uri().query(), "token")
+                    .ok_or_else(|| Self::reject_upgrade(StatusCode::UNAUTHORIZED, "Missing token"))?;
+                match verify_token(&auth.secret, &token, SystemTime::now()) {
+                    Ok(verified) => claims = Some(verified),
+                    Err(e) => return Err(Self::reject_upgrade(StatusCode::UNAUTHORIZED, &e.to_string())),
+                }
+            }
+            
+            Ok(response)
+        };
         
-        let ws_stream = accept_async(stream).await?;
+        let ws_config = websocket_config(self.config.limits.max_message_size);
+        let handshake_timeout = Duration::from_millis(self.config.signaling.handshake_timeout_ms);
+        let upgrade = accept_hdr_async_with_config(stream, check_upgrade, Some(ws_config));
+        let ws_stream = match complete_handshake(handshake_timeout, upgrade).await {
+            Some(Ok(ws_stream)) => ws_stream,
+            None => {
+                warn!("WebSocket upgrade from {} timed out", addr);
+                return Ok(());
+            }
+            Some(Err(Error::Http(response))) => {
+                warn!(
+                    "Rejected connection from {} ({} open from this address): {}",
+                    addr, self.connections.count_for_ip(addr.ip()), response.status()
+                );
+                return Ok(());
+            }
+            Some(Err(e)) => return Err(e.into()),
+        };
+        let _connection_guard = admission.ok();
         let (mut ws_sender, mut ws_receiver) = ws_stream.split();
         
-        // Create a channel for sending messages to this peer
-        let (message_sender, mut message_receiver) = mpsc::unbounded_channel::<SignalingMessage>();
+        // Create a bounded channel for sending messages to this peer
+        let (message_sender, mut message_receiver) =
+            mpsc::channel::<OutgoingFrame>(self.config.signaling.outgoing_queue_size.max(1));
         
-        // Extract user ID from connection (for now use a UUID, in production this would come from authentication)
-        let user_id = Uuid::new_v4().to_string();
-        let peer = Arc::new(Peer::new(user_id, message_sender));
+        // Take the identity from the join token; without authentication peers join anonymously
+        let user_id = match &claims {
+            Some(claims) => claims.user_id.clone(),
+            None => Uuid::new_v4().to_string(),
+        };
+        let peer = Arc::new(Peer::new(
+            user_id,
+            message_sender,
+            Duration::from_millis(self.config.signaling.slow_peer_timeout_ms),
+            RateLimiter::new(self.config.limits.requests_per_second, self.config.limits.request_burst),
+            RateLimiter::new(self.config.limits.relay_messages_per_second, self.config.limits.relay_burst),
+        ));
+        if let Some(claims) = claims {
+            peer.set_role(claims.role);
+            if let Some(display_name) = claims.display_name {
+                peer.set_token_display_name(display_name);
+            }
+        }
         
         // Get or create a default room (in production, this would be based on authentication/routing)
         let room_id = "default".to_string();
         let room = self.get_or_create_room(&room_id).await?;
         
-        // Add peer to room
-        room.add_peer(peer.clone()).await?;
+        // Add peer to room; wrong passwords, full rooms, banned users and
+        // duplicate sessions get `joinRefused` and a close with the error code
+        let joined = match room.check_password(&peer, password.as_deref()) {
+            Ok(()) => room.add_peer(peer.clone()).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = joined {
+            warn!("Refused peer {} from {}: {}", peer.user_id, addr, e);
+            let refusal = OutgoingFrame::new(&SignalingMessage::notification(
+                "joinRefused".to_string(),
+                Some(serde_json::to_value(SignalingError {
+                    error: e.to_string(),
+                    code: Some(e.code().to_string()),
+                })?),
+            ))?;
+            let _ = ws_sender.send(Message::Text(refusal.text.to_string())).await;
+            let close_frame = CloseFrame {
+                code: CloseCode::Policy,
+                reason: e.code().into(),
+            };
+            let _ = ws_sender.send(Message::Close(Some(close_frame))).await;
+            return Ok(());
+        }
         
         let peer_id = peer.id.clone();
         let room_clone = room.clone();
         
-        // Spawn task to handle outgoing messages
-        let outgoing_task = {
+        // Spawn task to handle outgoing messages; once the connection closes it
+        // flushes what is already queued, e.g. a kick notification
+        let (close_sender, mut close_receiver) = oneshot::channel::<()>();
+        let mut outgoing_task = {
             tokio::spawn(async move {
-                while let Some(message) = message_receiver.recv().await {
-                    let json = match serde_json::to_string(&message) {
-                        Ok(json) => json,
-                        Err(e) => {
-                            error!("Failed to serialize message: {}", e);
-                            continue;
+                loop {
+                    let frame = tokio::select! {
+                        frame = message_receiver.recv() => frame,
+                        _ = &mut close_receiver => {
+                            message_receiver.close();
+                            message_receiver.recv().await
                         }
                     };
-                    
-                    if let Err(e) = ws_sender.send(Message::Text(json)).await {
+                    let Some(frame) = frame else { break };
+                    // tungstenite 0.21 takes an owned `String`, so this is the one
+                    // per-recipient copy left; benches/broadcast.rs measures it
+                    if let Err(e) = ws_sender.send(Message::Text(frame.text.to_string())).await {
                         error!("Failed to send message: {}", e);
                         break;
                     }
                 }
+                let _ = ws_sender.close().await;
             })
         };
         
@@ -104,24 +288,57 @@ impl MediaSoupServer {
         let incoming_result = self.handle_incoming_messages(&mut ws_receiver, peer.clone(), room.clone()).await;
         
         // Cleanup
-        outgoing_task.abort();
+        let _ = close_sender.send(());
+        if tokio::time::timeout(CLOSE_DRAIN_TIMEOUT, &mut outgoing_task).await.is_err() {
+            outgoing_task.abort();
+        }
         if let Err(e) = room_clone.remove_peer(&peer_id).await {
             error!("Failed to remove peer {}: {}", peer_id, e);
         }
         
-        info!("Connection from {} closed", addr);
+        let stats = peer.queue_stats();
+        info!(
+            "Connection from {} closed (queue high water mark {}/{}, dropped {})",
+            addr, stats.high_water_mark, stats.capacity, stats.dropped
+        );
         incoming_result
     }
     
+    /// Build an HTTP error answer for a refused WebSocket upgrade
+    fn reject_upgrade(status: StatusCode, reason: &str) -> ErrorResponse {
+        let mut response = ErrorResponse::new(Some(reason.to_string()));
+        *response.status_mut() = status;
+        response
+    }
+    
     /// Handle incoming WebSocket messages
-    async fn handle_incoming_messages(
+    async fn handle_incoming_messages<S>(
         &self,
-        ws_receiver: &mut futures_util::stream::SplitStream<WebSocketStream<TcpStream>>,
+        ws_receiver: &mut futures_util::stream::SplitStream<WebSocketStream<S>>,
         peer: Arc<Peer>,
         room: Arc<Room>,
-    ) -> Result<()> {
-        while let Some(message) = ws_receiver.next().await {
-            let message = message?;
+    ) -> Result<()>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        loop {
+            let message = tokio::select! {
+                message = ws_receiver.next() => message,
+                _ = peer.disconnect_requested() => {
+                    info!("Closing connection for peer {}", peer.id);
+                    break;
+                }
+            };
+            
+            let message = match message {
+                Some(Ok(message)) => message,
+                Some(Err(Error::Capacity(e))) => {
+                    warn!("Closing connection for peer {}: {}", peer.id, e);
+                    break;
+                }
+                Some(Err(e)) => return Err(e.into()),
+                None => break,
+            };
             
             match message {
                 Message::Text(text) => {
@@ -149,52 +366,177 @@ impl MediaSoupServer {
         peer: &Arc<Peer>,
         room: &Arc<Room>,
     ) -> Result<()> {
-        let message: SignalingMessage = serde_json::from_str(text)?;
+        let max_violations = self.config.limits.max_violations;
+        let (message, allowed) = peer.receive_request(text, max_violations)?;
         debug!("Received message: {} from peer {}", message.method, peer.id);
         
-        let response = match message.method.as_str() {
+        let response = if !allowed {
+            Err(MediaSoupError::RateLimited(format!("Too many requests ({})", message.method)))
+        } else {
+            let response = self.dispatch_signaling_message(&message, peer, room).await;
+            if let Err(e) = &response {
+                if matches!(
+                    e,
+                    MediaSoupError::RateLimited(_) | MediaSoupError::LimitExceeded(_) | MediaSoupError::DataConsumerLimit(_)
+                ) {
+                    peer.record_violation(e, max_violations);
+                }
+            }
+            response
+        };
+        
+        // Send response if this was a request
+        if message.is_request() {
+            let response = response.unwrap_or_else(|e| {
+                warn!("Request {} from peer {} failed: {}", message.method, peer.id, e);
+                SignalingResponse {
+                    code: Some(e.code().to_string()),
+                    ..message.to_response(None, Some(e.to_string()))
+                }
+            });
+            peer.send_message(SignalingMessage {
+                id: Some(response.id),
+                method: "response".to_string(),
+                data: if let Some(error) = response.error {
+                    Some(serde_json::to_value(SignalingError { error, code: response.code })?)
+                } else {
+                    response.response
+                },
+            })?;
+            Ok(())
+        } else {
+            response.map(|_| ())
+        }
+    }
+    
+    /// Route a signaling message to its handler
+    async fn dispatch_signaling_message(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        if room.is_pending(&peer.id) {
+            return Err(MediaSoupError::Forbidden("Waiting for a GM to admit you".to_string()));
+        }
+        if room.get_peer(&peer.id).is_none() {
+            return Err(MediaSoupError::Forbidden("Not a member of this room".to_string()));
+        }
+        
+        match message.method.as_str() {
             "getRouterRtpCapabilities" => {
-                self.handle_get_router_rtp_capabilities(&message, room).await
+                self.handle_get_router_rtp_capabilities(message, room).await
             }
             "createWebRtcTransport" => {
-                self.handle_create_webrtc_transport(&message, peer, room).await
+                self.handle_create_webrtc_transport(message, peer, room).await
             }
             "connectTransport" => {
-                self.handle_connect_transport(&message, peer).await
+                self.handle_connect_transport(message, peer, room).await
             }
             "produce" => {
-                self.handle_produce(&message, peer, room).await
+                self.handle_produce(message, peer, room).await
             }
             "consume" => {
-                self.handle_consume(&message, peer, room).await
+                self.handle_consume(message, peer, room).await
             }
             "pauseProducer" => {
-                self.handle_pause_producer(&message, peer).await
+                self.handle_pause_producer(message, peer).await
             }
             "resumeProducer" => {
-                self.handle_resume_producer(&message, peer).await
+                self.handle_resume_producer(message, peer).await
+            }
+            "closeProducer" => {
+                self.handle_close_producer(message, peer, room).await
+            }
+            "getProducers" => {
+                self.handle_get_producers(message, peer, room).await
+            }
+            "getIceServers" => {
+                self.handle_get_ice_servers(message, peer).await
+            }
+            "restartIce" => {
+                self.handle_restart_ice(message, peer).await
+            }
+            "setMaxIncomingBitrate" => {
+                self.handle_set_max_incoming_bitrate(message, peer).await
+            }
+            "setConsumerPreferredLayers" => {
+                self.handle_set_consumer_preferred_layers(message, peer).await
+            }
+            "setConsumerPriority" => {
+                self.handle_set_consumer_priority(message, peer).await
+            }
+            "resumeConsumer" => {
+                self.handle_resume_consumer(message, peer, room).await
+            }
+            "requestConsumerKeyFrame" => {
+                self.handle_request_consumer_key_frame(message, peer, room).await
+            }
+            "produceData" => {
+                self.handle_produce_data(message, peer, room).await
+            }
+            "consumeData" => {
+                self.handle_consume_data(message, peer, room).await
+            }
+            "closeDataProducer" => {
+                self.handle_close_data_producer(message, peer, room).await
+            }
+            "getDataProducers" => {
+                self.handle_get_data_producers(message, peer, room).await
+            }
+            "getPeers" => {
+                self.handle_get_peers(message, peer, room).await
+            }
+            "updatePeerInfo" => {
+                self.handle_update_peer_info(message, peer, room).await
+            }
+            "broadcast" => {
+                self.handle_broadcast(message, peer, room).await
+            }
+            "sendToPeer" => {
+                self.handle_send_to_peer(message, peer, room).await
+            }
+            "muteUser" => {
+                self.handle_mute_user(message, peer, room).await
+            }
+            "unmuteUser" => {
+                self.handle_unmute_user(message, peer, room).await
+            }
+            "stopUserVideo" => {
+                self.handle_stop_user_video(message, peer, room).await
+            }
+            "kickUser" => {
+                self.handle_kick_user(message, peer, room).await
+            }
+            "closeRoom" => {
+                self.handle_close_room(message, peer, room).await
+            }
+            "banUser" => {
+                self.handle_ban_user(message, peer, room).await
+            }
+            "unbanUser" => {
+                self.handle_unban_user(message, peer, room).await
+            }
+            "getBannedUsers" => {
+                self.handle_get_banned_users(message, peer, room).await
+            }
+            "admit" => {
+                self.handle_admit(message, peer, room).await
+            }
+            "deny" => {
+                self.handle_deny(message, peer, room).await
+            }
+            "getJoinRequests" => {
+                self.handle_get_join_requests(message, peer, room).await
+            }
+            "setLastN" => {
+                self.handle_set_last_n(message, peer, room).await
             }
             _ => {
                 warn!("Unknown method: {}", message.method);
                 Ok(message.to_response(None, Some(format!("Unknown method: {}", message.method))))
             }
-        };
-        
-        // Send response if this was a request
-        if message.is_request() {
-            let response = response?;
-            peer.send_message(SignalingMessage {
-                id: Some(response.id),
-                method: "response".to_string(),
-                data: if let Some(error) = response.error {
-                    Some(serde_json::json!({ "error": error }))
-                } else {
-                    response.response
-                },
-            })?;
         }
-        
-        Ok(())
     }
     
     /// Handle getRouterRtpCapabilities request
@@ -226,18 +568,37 @@ impl MediaSoupServer {
             }
         };
         
-        // Use config listen IPs directly
-        let listen_ips = self.config.webrtc.listen_ips.clone();
+        if data.producing == Some(true) {
+            check_can_publish(peer)?;
+        }
+        
+        let max_transports = self.config.limits.max_transports_per_peer;
+        if peer.transports.len() >= max_transports {
+            return Err(MediaSoupError::LimitExceeded(format!("Maximum of {} transports per peer", max_transports)));
+        }
         
+        let webrtc = &self.config.webrtc;
         let transport = room.create_webrtc_transport(
             &peer.id,
-            listen_ips,
-            true,  // enable_udp
-            true,  // enable_tcp
-            true,  // prefer_udp
+            webrtc.listen_ips.clone(),
+            webrtc.enable_udp,
+            webrtc.enable_tcp,
+            webrtc.prefer_udp,
             data.sctp_capabilities.is_some(), // enable_sctp
+            Duration::from_secs(webrtc.transport_idle_timeout_secs),
+            Duration::from_secs(webrtc.transport_connect_timeout_secs),
         ).await?;
         
+        // Clients that don't say which direction a transport is for get both caps
+        if let (Some(bitrate), false) = (webrtc.max_incoming_bitrate, data.producing == Some(false)) {
+            transport.set_max_incoming_bitrate(bitrate).await
+                .map_err(|e| MediaSoupError::Transport(e.to_string()))?;
+        }
+        if let (Some(bitrate), false) = (webrtc.max_outgoing_bitrate, data.consuming == Some(false)) {
+            transport.set_max_outgoing_bitrate(bitrate).await
+                .map_err(|e| MediaSoupError::Transport(e.to_string()))?;
+        }
+        
         let response_data = serde_json::to_value(TransportCreatedResponse {
             id: transport.id().to_string(),
             ice_parameters: serde_json::to_value(transport.ice_parameters())?,
@@ -254,20 +615,72 @@ impl MediaSoupServer {
         &self,
         message: &SignalingMessage,
         peer: &Arc<Peer>,
+        room: &Arc<Room>,
     ) -> Result<SignalingResponse> {
         let data: ConnectTransportData = serde_json::from_value(
             message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
         )?;
         
+        let dtls_parameters: DtlsParameters = serde_json::from_value(data.dtls_parameters)?;
+        
+        room.connect_transport(
+            peer,
+            &data.transport_id,
+            dtls_parameters,
+            Duration::from_secs(self.config.webrtc.transport_connect_timeout_secs),
+        ).await?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle restartIce request
+    async fn handle_restart_ice(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+    ) -> Result<SignalingResponse> {
+        let data: RestartIceData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
         let transport = peer.transports.get(&data.transport_id)
-            .ok_or_else(|| MediaSoupError::TransportNotFound(data.transport_id.clone()))?;
+            .ok_or_else(|| MediaSoupError::TransportNotFound(data.transport_id.clone()))?
+            .clone();
         
-        let dtls_parameters: DtlsParameters = serde_json::from_value(data.dtls_parameters)?;
+        let ice_parameters = transport.restart_ice().await
+            .map_err(|e| MediaSoupError::Transport(e.to_string()))?;
+        
+        debug!("Restarted ICE on transport {} for peer {}", data.transport_id, peer.id);
+        
+        let response_data = serde_json::to_value(IceRestartedResponse {
+            ice_parameters: serde_json::to_value(ice_parameters)?,
+        })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle setMaxIncomingBitrate request
+    async fn handle_set_max_incoming_bitrate(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+    ) -> Result<SignalingResponse> {
+        let data: SetMaxIncomingBitrateData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let transport = peer.transports.get(&data.transport_id)
+            .ok_or_else(|| MediaSoupError::TransportNotFound(data.transport_id.clone()))?
+            .clone();
+        
+        let bitrate = clamp_incoming_bitrate(data.bitrate, self.config.webrtc.max_incoming_bitrate);
         
-        transport.connect(WebRtcTransportRemoteParameters { dtls_parameters }).await
+        transport.set_max_incoming_bitrate(bitrate).await
             .map_err(|e| MediaSoupError::Transport(e.to_string()))?;
         
-        Ok(message.to_response(Some(serde_json::json!({})), None))
+        debug!("Set max incoming bitrate of transport {} to {}", data.transport_id, bitrate);
+        
+        Ok(message.to_response(Some(serde_json::json!({ "bitrate": bitrate })), None))
     }
     
     /// Handle produce request
@@ -286,8 +699,12 @@ impl MediaSoupServer {
             "video" => MediaKind::Video,
             _ => return Err(MediaSoupError::InvalidRequest(format!("Invalid media kind: {}", data.kind))),
         };
+        if kind == MediaKind::Audio && peer.is_muted() {
+            return Err(MediaSoupError::Forbidden("Muted by a GM".to_string()));
+        }
         
         let rtp_parameters: RtpParameters = serde_json::from_value(data.rtp_parameters)?;
+        validate_encodings(kind, &rtp_parameters)?;
         
         let producer = room.create_producer(
             &peer.id,
@@ -295,6 +712,7 @@ impl MediaSoupServer {
             kind,
             rtp_parameters,
             data.app_data,
+            self.config.limits.max_producers_per_peer,
         ).await?;
         
         let response_data = serde_json::to_value(ProducedResponse {
@@ -315,6 +733,11 @@ impl MediaSoupServer {
             message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
         )?;
         
+        let max_consumers = self.config.limits.max_consumers_per_peer;
+        if peer.consumers.len() >= max_consumers {
+            return Err(MediaSoupError::LimitExceeded(format!("Maximum of {} consumers per peer", max_consumers)));
+        }
+        
         let rtp_capabilities: RtpCapabilities = serde_json::from_value(data.rtp_capabilities)?;
         
         let consumer = room.create_consumer(
@@ -329,11 +752,123 @@ impl MediaSoupServer {
             producer_id: data.producer_id,
             kind: format!("{:?}", consumer.kind()),
             rtp_parameters: serde_json::to_value(consumer.rtp_parameters())?,
+            paused: consumer.paused(),
+            producer_paused: consumer.producer_paused(),
         })?;
         
         Ok(message.to_response(Some(response_data), None))
     }
     
+    /// Handle setConsumerPreferredLayers request
+    async fn handle_set_consumer_preferred_layers(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+    ) -> Result<SignalingResponse> {
+        let data: SetConsumerPreferredLayersData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let consumer = peer.consumers.get(&data.consumer_id)
+            .ok_or_else(|| MediaSoupError::ConsumerNotFound(data.consumer_id.clone()))?
+            .clone();
+        
+        consumer.set_preferred_layers(ConsumerLayers {
+            spatial_layer: data.spatial_layer,
+            temporal_layer: data.temporal_layer,
+        }).await
+            .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle setConsumerPriority request
+    async fn handle_set_consumer_priority(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+    ) -> Result<SignalingResponse> {
+        let data: SetConsumerPriorityData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        if data.priority == 0 {
+            return Err(MediaSoupError::InvalidRequest("Priority must be between 1 and 255".to_string()));
+        }
+        
+        let consumer = peer.consumers.get(&data.consumer_id)
+            .ok_or_else(|| MediaSoupError::ConsumerNotFound(data.consumer_id.clone()))?
+            .clone();
+        
+        consumer.set_priority(data.priority).await
+            .map_err(|e| MediaSoupError::Consumer(e.to_string()))?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle setLastN request
+    async fn handle_set_last_n(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: SetLastNData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        match data.scope.as_deref() {
+            None | Some("peer") => peer.set_last_n(data.last_n, data.pinned_user_ids),
+            Some("room") => {
+                require_gm(peer)?;
+                room.set_last_n(data.last_n, data.pinned_user_ids);
+            }
+            Some(scope) => return Err(MediaSoupError::InvalidRequest(format!("Invalid scope: {}", scope))),
+        }
+        
+        room.apply_last_n().await;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle resumeConsumer request
+    async fn handle_resume_consumer(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: Value = message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?;
+        let consumer_id = data.get("consumerId")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| MediaSoupError::InvalidRequest("Missing consumerId".to_string()))?;
+        
+        let paused = room.resume_consumer(peer, consumer_id).await?;
+        
+        Ok(message.to_response(Some(serde_json::json!({ "paused": paused })), None))
+    }
+    
+    /// Handle requestConsumerKeyFrame request
+    async fn handle_request_consumer_key_frame(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: Value = message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?;
+        let consumer_id = data.get("consumerId")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| MediaSoupError::InvalidRequest("Missing consumerId".to_string()))?;
+        
+        let consumer = peer.consumers.get(consumer_id)
+            .ok_or_else(|| MediaSoupError::ConsumerNotFound(consumer_id.to_string()))?
+            .clone();
+        
+        let requested = room.request_key_frame(&consumer).await?;
+        
+        Ok(message.to_response(Some(serde_json::json!({ "requested": requested })), None))
+    }
+    
     /// Handle pauseProducer request
     async fn handle_pause_producer(
         &self,
@@ -367,6 +902,9 @@ impl MediaSoupServer {
         
         let producer = peer.producers.get(producer_id)
             .ok_or_else(|| MediaSoupError::ProducerNotFound(producer_id.to_string()))?;
+        if producer.kind() == MediaKind::Audio && peer.is_muted() {
+            return Err(MediaSoupError::Forbidden("Muted by a GM".to_string()));
+        }
         
         producer.resume().await
             .map_err(|e| MediaSoupError::Producer(e.to_string()))?;
@@ -374,31 +912,529 @@ impl MediaSoupServer {
         Ok(message.to_response(Some(serde_json::json!({})), None))
     }
     
-    /// Get or create a room
-    async fn get_or_create_room(&self, room_id: &str) -> Result<Arc<Room>> {
-        if let Some(room) = self.rooms.get(room_id) {
-            Ok(room.clone())
-        } else {
-            let worker = self.worker_manager.get_worker().await?;
-            let room = Arc::new(Room::new(room_id.to_string(), &worker).await?);
-            self.rooms.insert(room_id.to_string(), room.clone());
-            Ok(room)
+    /// Handle closeProducer request
+    async fn handle_close_producer(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: Value = message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?;
+        let producer_id = data.get("producerId")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| MediaSoupError::InvalidRequest("Missing producerId".to_string()))?;
+        
+        if !peer.producers.contains_key(producer_id) {
+            return Err(MediaSoupError::ProducerNotFound(producer_id.to_string()));
         }
-    }
-}
-
-/// Worker manager to handle MediaSoup workers
-pub struct CustomWorkerManager {
-    workers: Vec<Worker>,
-    current_worker: std::sync::atomic::AtomicUsize,
-    worker_manager: WorkerManager,
-}
-
-impl CustomWorkerManager {
-    /// Create a new worker manager
-    pub async fn new(config: &Config) -> Result<Self> {
-        let worker_manager = WorkerManager::new();
-        let mut workers = Vec::new();
+        
+        room.close_producer(producer_id).await?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle getProducers request
+    async fn handle_get_producers(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let producers = room.list_producers()
+            .into_iter()
+            .filter(|entry| entry.peer_id != peer.id)
+            .map(|entry| NewProducerNotification {
+                id: entry.producer.id().to_string(),
+                user_id: entry.user_id,
+                kind: format!("{:?}", entry.producer.kind()),
+                app_data: entry.app_data,
+            })
+            .collect();
+        
+        let response_data = serde_json::to_value(ProducersResponse { producers })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle produceData request
+    async fn handle_produce_data(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: ProduceDataData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        check_can_publish(peer)?;
+        
+        let max_data_producers = self.config.limits.max_data_producers_per_peer;
+        if peer.data_producers.len() >= max_data_producers {
+            return Err(MediaSoupError::LimitExceeded(format!("Maximum of {} data producers per peer", max_data_producers)));
+        }
+        
+        let sctp_stream_parameters: SctpStreamParameters = serde_json::from_value(data.sctp_stream_parameters)?;
+        
+        let data_producer = room.create_data_producer(
+            &peer.id,
+            &data.transport_id,
+            sctp_stream_parameters,
+            data.label,
+            data.protocol,
+            data.app_data,
+        ).await?;
+        
+        let response_data = serde_json::to_value(DataProducedResponse {
+            id: data_producer.id().to_string(),
+        })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle consumeData request
+    async fn handle_consume_data(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: ConsumeDataData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let max_data_consumers = self.config.limits.max_data_consumers_per_peer;
+        if peer.data_consumers.len() >= max_data_consumers {
+            return Err(MediaSoupError::DataConsumerLimit(format!(
+                "Maximum of {} data consumers per peer",
+                max_data_consumers
+            )));
+        }
+        
+        let data_consumer = room.create_data_consumer(
+            &peer.id,
+            &data.transport_id,
+            &data.data_producer_id,
+        ).await?;
+        
+        let response_data = serde_json::to_value(DataConsumedResponse {
+            id: data_consumer.id().to_string(),
+            data_producer_id: data.data_producer_id,
+            sctp_stream_parameters: serde_json::to_value(data_consumer.sctp_stream_parameters())?,
+            label: data_consumer.label().clone(),
+            protocol: data_consumer.protocol().clone(),
+        })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle closeDataProducer request
+    async fn handle_close_data_producer(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: Value = message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?;
+        let data_producer_id = data.get("dataProducerId")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| MediaSoupError::InvalidRequest("Missing dataProducerId".to_string()))?;
+        
+        if !peer.data_producers.contains_key(data_producer_id) {
+            return Err(MediaSoupError::DataProducerNotFound(data_producer_id.to_string()));
+        }
+        
+        room.close_data_producer(data_producer_id).await?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle getDataProducers request
+    async fn handle_get_data_producers(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data_producers = room.list_data_producers()
+            .into_iter()
+            .filter(|entry| entry.peer_id != peer.id)
+            .map(|entry| NewDataProducerNotification {
+                id: entry.data_producer.id().to_string(),
+                user_id: entry.user_id,
+                label: entry.data_producer.label().clone(),
+                protocol: entry.data_producer.protocol().clone(),
+                app_data: entry.app_data,
+            })
+            .collect();
+        
+        let response_data = serde_json::to_value(DataProducersResponse { data_producers })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle getPeers request
+    async fn handle_get_peers(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let response_data = serde_json::to_value(PeersResponse {
+            peers: room.list_peer_info(peer),
+        })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle updatePeerInfo request
+    async fn handle_update_peer_info(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: UpdatePeerInfoData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let info = peer.update_info(data.display_name, data.devices)?;
+        let info = serde_json::to_value(info)?;
+        
+        room.announce_presence(
+            peer,
+            SignalingMessage::notification("peerUpdated".to_string(), Some(info.clone())),
+        )?;
+        
+        Ok(message.to_response(Some(info), None))
+    }
+    
+    /// Handle broadcast request
+    async fn handle_broadcast(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: BroadcastData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let notification = peer.relay_message(true, data.payload, self.config.limits.max_relay_payload_size)?;
+        room.broadcast_to_others(&peer.id, notification).await?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle sendToPeer request
+    async fn handle_send_to_peer(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        let data: SendToPeerData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let notification = peer.relay_message(false, data.payload, self.config.limits.max_relay_payload_size)?;
+        // Hidden peers are reported as missing rather than refused
+        if room.send_to_user(peer, &data.user_id, notification).await? == 0 {
+            return Err(MediaSoupError::PeerNotFound(data.user_id));
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle muteUser request
+    async fn handle_mute_user(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        for target in Self::moderation_targets(message, peer, room)? {
+            let producer_ids = target.mute().await?;
+            target.moderate(ModerationAction::Mute, Some(peer.user_id.clone()), producer_ids)?;
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle unmuteUser request
+    async fn handle_unmute_user(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        for target in Self::moderation_targets(message, peer, room)? {
+            target.unmute();
+            target.moderate(ModerationAction::Unmute, Some(peer.user_id.clone()), Vec::new())?;
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle stopUserVideo request
+    async fn handle_stop_user_video(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        for target in Self::moderation_targets(message, peer, room)? {
+            let producer_ids = room.close_producers_of_kind(&target.id, MediaKind::Video).await?;
+            target.moderate(ModerationAction::StopVideo, Some(peer.user_id.clone()), producer_ids)?;
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle kickUser request
+    async fn handle_kick_user(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        for target in Self::moderation_targets(message, peer, room)? {
+            target.moderate(ModerationAction::Kick, Some(peer.user_id.clone()), Vec::new())?;
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle closeRoom request
+    ///
+    /// Every peer, including the GM, is disconnected; the next connection
+    /// starts a fresh room.
+    async fn handle_close_room(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        self.rooms.remove(&room.id);
+        info!("Room {} closed by {}", room.id, peer.user_id);
+        
+        for target in room.peers.iter().filter(|target| target.id != peer.id) {
+            if let Err(e) = target.moderate(ModerationAction::CloseRoom, Some(peer.user_id.clone()), Vec::new()) {
+                warn!("Failed to notify peer {} of room close: {}", target.id, e);
+            }
+        }
+        room.disconnect_all("room closed");
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle banUser request
+    async fn handle_ban_user(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        let data: ModerateUserData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        if data.user_id == peer.user_id {
+            return Err(MediaSoupError::InvalidRequest("Cannot ban yourself".to_string()));
+        }
+        
+        room.ban_user(&data.user_id, Some(peer.user_id.clone()))?;
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle unbanUser request
+    async fn handle_unban_user(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        let data: ModerateUserData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        let banned = room.unban_user(&data.user_id);
+        
+        Ok(message.to_response(Some(serde_json::json!({ "banned": banned })), None))
+    }
+    
+    /// Handle getBannedUsers request
+    async fn handle_get_banned_users(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        let response_data = serde_json::to_value(BannedUsersResponse {
+            user_ids: room.banned_user_ids(),
+        })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Handle admit request
+    async fn handle_admit(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        let data: ModerateUserData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        if room.admit(&data.user_id).await? == 0 {
+            return Err(MediaSoupError::PeerNotFound(data.user_id));
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle deny request
+    async fn handle_deny(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        let data: ModerateUserData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        if room.deny(&data.user_id) == 0 {
+            return Err(MediaSoupError::PeerNotFound(data.user_id));
+        }
+        
+        Ok(message.to_response(Some(serde_json::json!({})), None))
+    }
+    
+    /// Handle getJoinRequests request
+    async fn handle_get_join_requests(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+        room: &Arc<Room>,
+    ) -> Result<SignalingResponse> {
+        require_gm(peer)?;
+        
+        let response_data = serde_json::to_value(PeersResponse {
+            peers: room.list_join_requests(),
+        })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Check the requester is a GM and resolve the target user's sessions
+    fn moderation_targets(message: &SignalingMessage, peer: &Peer, room: &Room) -> Result<Vec<Arc<Peer>>> {
+        require_gm(peer)?;
+        
+        let data: ModerateUserData = serde_json::from_value(
+            message.data.clone().ok_or_else(|| MediaSoupError::InvalidRequest("Missing data".to_string()))?
+        )?;
+        
+        let targets = room.peers_for_user(&data.user_id);
+        if targets.is_empty() {
+            return Err(MediaSoupError::PeerNotFound(data.user_id));
+        }
+        info!("{} applying moderation to {} in room {}", peer.user_id, data.user_id, room.id);
+        
+        Ok(targets)
+    }
+    
+    /// Handle getIceServers request
+    async fn handle_get_ice_servers(
+        &self,
+        message: &SignalingMessage,
+        peer: &Arc<Peer>,
+    ) -> Result<SignalingResponse> {
+        let ice_servers = self.config.turn
+            .iter()
+            .map(|turn| issue_credentials(turn, &peer.user_id, SystemTime::now()))
+            .collect();
+        
+        let response_data = serde_json::to_value(IceServersResponse { ice_servers })?;
+        
+        Ok(message.to_response(Some(response_data), None))
+    }
+    
+    /// Get or create a room
+    async fn get_or_create_room(&self, room_id: &str) -> Result<Arc<Room>> {
+        if let Some(room) = self.rooms.get(room_id) {
+            Ok(room.clone())
+        } else {
+            let (worker, webrtc_server) = self.worker_manager.get_worker().await?;
+            let outgoing_bitrate = OutgoingBitratePolicy {
+                max_per_transport: self.config.webrtc.max_outgoing_bitrate,
+                room_budget: self.config.webrtc.room_outgoing_bitrate,
+            };
+            let room = Room::new(
+                room_id.to_string(),
+                worker,
+                webrtc_server,
+                outgoing_bitrate,
+                &self.config.room,
+                self.bans.clone(),
+            ).await?;
+            self.rooms.insert(room_id.to_string(), room.clone());
+            Ok(room)
+        }
+    }
+}
+
+/// Listen infos for worker `index`'s WebRtcServer: every listen IP on the
+/// configured UDP and TCP ports, offset by the worker index
+pub fn webrtc_server_listen_infos(
+    listen_ips: &[ListenIp],
+    server_config: &WebRtcServerConfig,
+    index: usize,
+) -> Result<Vec<ListenInfo>> {
+    let port_for = |base: u16| {
+        u16::try_from(index)
+            .ok()
+            .and_then(|offset| base.checked_add(offset))
+            .ok_or_else(|| MediaSoupError::Config(format!("WebRtcServer port {} + {} out of range", base, index)))
+    };
+    
+    let mut listen_infos = Vec::new();
+    for listen_ip in listen_ips {
+        if let Some(udp_port) = server_config.udp_port {
+            listen_infos.push(listen_info(listen_ip, Protocol::Udp, Some(port_for(udp_port)?))?);
+        }
+        if let Some(tcp_port) = server_config.tcp_port {
+            listen_infos.push(listen_info(listen_ip, Protocol::Tcp, Some(port_for(tcp_port)?))?);
+        }
+    }
+    Ok(listen_infos)
+}
+
+/// Worker manager to handle MediaSoup workers
+pub struct CustomWorkerManager {
+    workers: Vec<Worker>,
+    webrtc_servers: Vec<WebRtcServer>,
+    current_worker: std::sync::atomic::AtomicUsize,
+    worker_manager: WorkerManager,
+}
+
+impl CustomWorkerManager {
+    /// Create a new worker manager
+    pub async fn new(config: &Config) -> Result<Self> {
+        let worker_manager = WorkerManager::new();
+        let mut workers = Vec::new();
+        let mut webrtc_servers = Vec::new();
         
         for i in 0..config.worker.num_workers {
             let worker_settings = WorkerSettings::default();
@@ -406,20 +1442,51 @@ impl CustomWorkerManager {
             
             let worker = worker_manager.create_worker(worker_settings).await?;
             info!("Created MediaSoup worker {} with ID {}", i, worker.id());
+            
+            if let Some(server_config) = &config.webrtc.webrtc_server {
+                let webrtc_server = Self::create_webrtc_server(&worker, i, server_config, config).await?;
+                webrtc_servers.push(webrtc_server);
+            }
+            
             workers.push(worker);
         }
         
         Ok(Self {
             workers,
+            webrtc_servers,
             current_worker: std::sync::atomic::AtomicUsize::new(0),
             worker_manager,
         })
     }
     
-    /// Get a worker (round-robin)
-    pub async fn get_worker(&self) -> Result<&Worker> {
+    /// Create a WebRtcServer listening on one UDP and/or TCP port for a worker
+    async fn create_webrtc_server(
+        worker: &Worker,
+        index: usize,
+        server_config: &WebRtcServerConfig,
+        config: &Config,
+    ) -> Result<WebRtcServer> {
+        let listen_infos = webrtc_server_listen_infos(&config.webrtc.listen_ips, server_config, index)?;
+        let ports: Vec<(Protocol, Option<u16>)> = listen_infos.iter().map(|info| (info.protocol, info.port)).collect();
+        
+        let listen_infos = WebRtcServerListenInfos::try_from(listen_infos).map_err(|_| {
+            MediaSoupError::Config("WebRtcServer needs at least one listen IP and port".to_string())
+        })?;
+        
+        let webrtc_server = worker
+            .create_webrtc_server(WebRtcServerOptions::new(listen_infos))
+            .await?;
+        
+        info!("Created WebRtcServer {} for worker {} on {:?}", webrtc_server.id(), index, ports);
+        
+        Ok(webrtc_server)
+    }
+    
+    /// Get a worker and its WebRtcServer, if any (round-robin)
+    pub async fn get_worker(&self) -> Result<(&Worker, Option<WebRtcServer>)> {
         let index = self.current_worker.fetch_add(1, std::sync::atomic::Ordering::Relaxed) % self.workers.len();
-        self.workers.get(index).ok_or_else(|| MediaSoupError::Config("No workers available".to_string()))
+        let worker = self.workers.get(index).ok_or_else(|| MediaSoupError::Config("No workers available".to_string()))?;
+        Ok((worker, self.webrtc_servers.get(index).cloned()))
     }
     
     // TODO: Re-implement log level and tag parsing once we understand the new API
@@ -1,7 +1,12 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Notifications that only carry the latest state and may be dropped when a
+/// peer's outgoing queue is full
+pub const LOSSY_NOTIFICATIONS: &[&str] = &["audioLevels", "activeSpeaker", "stats"];
+
 /// WebSocket signaling message for communication between client and server
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignalingMessage {
@@ -10,6 +15,25 @@ pub struct SignalingMessage {
     pub data: Option<Value>,
 }
 
+/// A signaling message serialized once and shared between every peer it is sent to
+#[derive(Debug, Clone)]
+pub struct OutgoingFrame {
+    pub method: Arc<str>,
+    pub lossy: bool,
+    pub text: Arc<str>,
+}
+
+impl OutgoingFrame {
+    /// Serialize a message into a frame ready to be written to a WebSocket
+    pub fn new(message: &SignalingMessage) -> serde_json::Result<Self> {
+        Ok(Self {
+            method: Arc::from(message.method.as_str()),
+            lossy: message.is_lossy(),
+            text: Arc::from(serde_json::to_string(message)?),
+        })
+    }
+}
+
 /// Request message from client
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SignalingRequest {
@@ -24,6 +48,17 @@ pub struct SignalingResponse {
     pub id: String,
     pub response: Option<Value>,
     pub error: Option<String>,
+    pub code: Option<String>,
+}
+
+/// Error payload of a failed request or refused join
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalingError {
+    pub error: String,
+    
+    /// Stable error code such as `ROOM_FULL`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
 
 /// Notification message to client (no response expected)
@@ -56,6 +91,93 @@ pub enum SignalingMethod {
     
     #[serde(rename = "resumeProducer")]
     ResumeProducer,
+    
+    #[serde(rename = "closeProducer")]
+    CloseProducer,
+    
+    #[serde(rename = "getProducers")]
+    GetProducers,
+    
+    #[serde(rename = "getIceServers")]
+    GetIceServers,
+    
+    #[serde(rename = "restartIce")]
+    RestartIce,
+    
+    #[serde(rename = "setMaxIncomingBitrate")]
+    SetMaxIncomingBitrate,
+    
+    #[serde(rename = "setConsumerPreferredLayers")]
+    SetConsumerPreferredLayers,
+    
+    #[serde(rename = "setConsumerPriority")]
+    SetConsumerPriority,
+    
+    #[serde(rename = "setLastN")]
+    SetLastN,
+    
+    #[serde(rename = "resumeConsumer")]
+    ResumeConsumer,
+    
+    #[serde(rename = "requestConsumerKeyFrame")]
+    RequestConsumerKeyFrame,
+    
+    #[serde(rename = "produceData")]
+    ProduceData,
+    
+    #[serde(rename = "consumeData")]
+    ConsumeData,
+    
+    #[serde(rename = "closeDataProducer")]
+    CloseDataProducer,
+    
+    #[serde(rename = "getDataProducers")]
+    GetDataProducers,
+    
+    #[serde(rename = "getPeers")]
+    GetPeers,
+    
+    #[serde(rename = "updatePeerInfo")]
+    UpdatePeerInfo,
+    
+    #[serde(rename = "broadcast")]
+    Broadcast,
+    
+    #[serde(rename = "sendToPeer")]
+    SendToPeer,
+    
+    #[serde(rename = "muteUser")]
+    MuteUser,
+    
+    #[serde(rename = "unmuteUser")]
+    UnmuteUser,
+    
+    #[serde(rename = "stopUserVideo")]
+    StopUserVideo,
+    
+    #[serde(rename = "kickUser")]
+    KickUser,
+    
+    #[serde(rename = "closeRoom")]
+    CloseRoom,
+    
+    #[serde(rename = "banUser")]
+    BanUser,
+    
+    #[serde(rename = "unbanUser")]
+    UnbanUser,
+    
+    #[serde(rename = "getBannedUsers")]
+    GetBannedUsers,
+    
+    #[serde(rename = "admit")]
+    Admit,
+    
+    #[serde(rename = "deny")]
+    Deny,
+    
+    #[serde(rename = "getJoinRequests")]
+    GetJoinRequests,
 }
 
 /// Transport connection data
@@ -68,6 +190,57 @@ pub struct ConnectTransportData {
     pub dtls_parameters: Value,
 }
 
+/// ICE restart data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestartIceData {
+    #[serde(rename = "transportId")]
+    pub transport_id: String,
+}
+
+/// Max incoming bitrate data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetMaxIncomingBitrateData {
+    #[serde(rename = "transportId")]
+    pub transport_id: String,
+    
+    pub bitrate: u32,
+}
+
+/// Consumer preferred layers data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetConsumerPreferredLayersData {
+    #[serde(rename = "consumerId")]
+    pub consumer_id: String,
+    
+    #[serde(rename = "spatialLayer")]
+    pub spatial_layer: u8,
+    
+    #[serde(rename = "temporalLayer")]
+    pub temporal_layer: Option<u8>,
+}
+
+/// Consumer priority data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetConsumerPriorityData {
+    #[serde(rename = "consumerId")]
+    pub consumer_id: String,
+    
+    pub priority: u8,
+}
+
+/// Last-N forwarding data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLastNData {
+    #[serde(rename = "lastN")]
+    pub last_n: Option<usize>,
+    
+    #[serde(rename = "pinnedUserIds", default)]
+    pub pinned_user_ids: Vec<String>,
+    
+    /// "peer" (default) or "room"
+    pub scope: Option<String>,
+}
+
 /// Producer creation data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProduceData {
@@ -96,6 +269,66 @@ pub struct ConsumeData {
     pub rtp_capabilities: Value,
 }
 
+/// Peer info update data; omitted fields are left unchanged
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdatePeerInfoData {
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    
+    pub devices: Option<PeerDevices>,
+}
+
+/// Opaque payload relayed to the rest of the room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BroadcastData {
+    pub payload: Value,
+}
+
+/// Opaque payload relayed to a single user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SendToPeerData {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    
+    pub payload: Value,
+}
+
+/// Target of a GM moderation request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModerateUserData {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+}
+
+/// Data producer creation data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProduceDataData {
+    #[serde(rename = "transportId")]
+    pub transport_id: String,
+    
+    #[serde(rename = "sctpStreamParameters")]
+    pub sctp_stream_parameters: Value,
+    
+    #[serde(default)]
+    pub label: String,
+    
+    #[serde(default)]
+    pub protocol: String,
+    
+    #[serde(rename = "appData")]
+    pub app_data: Option<Value>,
+}
+
+/// Data consumer creation data
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumeDataData {
+    #[serde(rename = "transportId")]
+    pub transport_id: String,
+    
+    #[serde(rename = "dataProducerId")]
+    pub data_producer_id: String,
+}
+
 /// WebRTC transport creation data
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateWebRtcTransportData {
@@ -124,12 +357,56 @@ pub struct TransportCreatedResponse {
     pub sctp_parameters: Option<Value>,
 }
 
+/// ICE restart response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceRestartedResponse {
+    #[serde(rename = "iceParameters")]
+    pub ice_parameters: Value,
+}
+
+/// ICE server entry in RTCIceServer format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceServer {
+    pub urls: Vec<String>,
+    pub username: String,
+    pub credential: String,
+}
+
+/// ICE servers response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IceServersResponse {
+    #[serde(rename = "iceServers")]
+    pub ice_servers: Vec<IceServer>,
+}
+
 /// Producer creation response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProducedResponse {
     pub id: String,
 }
 
+/// Data producer creation response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataProducedResponse {
+    pub id: String,
+}
+
+/// Data consumer creation response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataConsumedResponse {
+    pub id: String,
+    
+    #[serde(rename = "dataProducerId")]
+    pub data_producer_id: String,
+    
+    #[serde(rename = "sctpStreamParameters")]
+    pub sctp_stream_parameters: Value,
+    
+    pub label: String,
+    
+    pub protocol: String,
+}
+
 /// Consumer creation response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConsumedResponse {
@@ -142,6 +419,109 @@ pub struct ConsumedResponse {
     
     #[serde(rename = "rtpParameters")]
     pub rtp_parameters: Value,
+    
+    /// Always true; send resumeConsumer once the track is ready
+    pub paused: bool,
+    
+    #[serde(rename = "producerPaused")]
+    pub producer_paused: bool,
+}
+
+/// Role of a participant in the room
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PeerRole {
+    #[default]
+    Player,
+    Gm,
+    /// Listen-only viewer that never publishes
+    Spectator,
+}
+
+/// Capture devices a participant has available
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PeerDevices {
+    pub audio: bool,
+    pub video: bool,
+}
+
+/// Participant entry for peerJoined, peerUpdated and getPeers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerInfo {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    
+    #[serde(rename = "displayName")]
+    pub display_name: Option<String>,
+    
+    pub role: PeerRole,
+    
+    pub devices: PeerDevices,
+}
+
+/// User IDs banned from the room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BannedUsersResponse {
+    #[serde(rename = "userIds")]
+    pub user_ids: Vec<String>,
+}
+
+/// Participants in the room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeersResponse {
+    pub peers: Vec<PeerInfo>,
+}
+
+/// Peer left notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerLeftNotification {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+}
+
+/// Relayed message notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeerMessageNotification {
+    #[serde(rename = "fromUserId")]
+    pub from_user_id: String,
+    
+    /// True when sent with `broadcast`, false for `sendToPeer`
+    pub broadcast: bool,
+    
+    pub payload: Value,
+}
+
+/// Moderation action taken by a GM
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModerationAction {
+    Mute,
+    Unmute,
+    StopVideo,
+    Kick,
+    Ban,
+    CloseRoom,
+}
+
+/// Notification sent to a peer affected by a moderation action
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModeratedNotification {
+    pub action: ModerationAction,
+    
+    /// None when applied through the admin API
+    #[serde(rename = "byUserId")]
+    pub by_user_id: Option<String>,
+    
+    /// The peer's own producers that were paused or closed
+    #[serde(rename = "producerIds")]
+    pub producer_ids: Vec<String>,
+}
+
+/// Join request withdrawn because the waiting peer disconnected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinRequestCancelledNotification {
+    #[serde(rename = "userId")]
+    pub user_id: String,
 }
 
 /// New producer notification
@@ -153,6 +533,120 @@ pub struct NewProducerNotification {
     pub user_id: String,
     
     pub kind: String,
+    
+    #[serde(rename = "appData")]
+    pub app_data: Option<Value>,
+}
+
+/// Dominant speaker changed notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveSpeakerNotification {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    
+    #[serde(rename = "producerId")]
+    pub producer_id: String,
+}
+
+/// Volume of one audio producer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioLevel {
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    
+    #[serde(rename = "producerId")]
+    pub producer_id: String,
+    
+    /// Average volume in dBov, from -127 (silence) to 0
+    pub volume: i8,
+}
+
+/// Audio levels notification; empty when the room is silent
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AudioLevelsNotification {
+    pub levels: Vec<AudioLevel>,
+}
+
+/// New data producer notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewDataProducerNotification {
+    pub id: String,
+    
+    #[serde(rename = "userId")]
+    pub user_id: String,
+    
+    pub label: String,
+    
+    pub protocol: String,
+    
+    #[serde(rename = "appData")]
+    pub app_data: Option<Value>,
+}
+
+/// Existing data producers in the room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataProducersResponse {
+    #[serde(rename = "dataProducers")]
+    pub data_producers: Vec<NewDataProducerNotification>,
+}
+
+/// Data producer closed notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataProducerClosedNotification {
+    #[serde(rename = "dataProducerId")]
+    pub data_producer_id: String,
+}
+
+/// Existing producers in the room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProducersResponse {
+    pub producers: Vec<NewProducerNotification>,
+}
+
+/// Transport ICE/DTLS state change notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportStateChangedNotification {
+    #[serde(rename = "transportId")]
+    pub transport_id: String,
+    
+    #[serde(rename = "iceState")]
+    pub ice_state: Option<Value>,
+    
+    #[serde(rename = "dtlsState")]
+    pub dtls_state: Option<Value>,
+}
+
+/// Consumer layers changed notification
+///
+/// Both layers are `None` when the consumer is not receiving any layer.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerLayersChangedNotification {
+    #[serde(rename = "consumerId")]
+    pub consumer_id: String,
+    
+    #[serde(rename = "spatialLayer")]
+    pub spatial_layer: Option<u8>,
+    
+    #[serde(rename = "temporalLayer")]
+    pub temporal_layer: Option<u8>,
+}
+
+/// Consumer paused or resumed by the server notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsumerPausedNotification {
+    #[serde(rename = "consumerId")]
+    pub consumer_id: String,
+    
+    pub reason: String,
+}
+
+/// Transport closed by the server notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportClosedNotification {
+    #[serde(rename = "transportId")]
+    pub transport_id: String,
+    
+    pub reason: String,
 }
 
 /// Producer closed notification
@@ -187,6 +681,7 @@ impl SignalingMessage {
             id: self.id.clone().unwrap_or_default(),
             response,
             error,
+            code: None,
         }
     }
     
@@ -199,4 +694,9 @@ impl SignalingMessage {
     pub fn is_notification(&self) -> bool {
         self.id.is_none()
     }
+    
+    /// Check if this message may be dropped under backpressure
+    pub fn is_lossy(&self) -> bool {
+        self.is_notification() && LOSSY_NOTIFICATIONS.contains(&self.method.as_str())
+    }
 }
\ No newline at end of file
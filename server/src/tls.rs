@@ -0,0 +1,136 @@
+use crate::config::TlsConfig;
+use crate::error::{MediaSoupError, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+use tracing::{error, info};
+
+/// Holds the current TLS acceptor and swaps it when certificates change
+pub struct CertificateStore {
+    config: TlsConfig,
+    acceptor: RwLock<TlsAcceptor>,
+    modified: Mutex<Option<SystemTime>>,
+}
+
+impl CertificateStore {
+    /// Load the certificate and key named in the config
+    pub fn load(config: TlsConfig) -> Result<Self> {
+        let acceptor = Self::build_acceptor(&config)?;
+        let modified = Self::last_modified(&config);
+        
+        info!("Loaded TLS certificate from {}", config.cert_path.display());
+        
+        Ok(Self {
+            config,
+            acceptor: RwLock::new(acceptor),
+            modified: Mutex::new(modified),
+        })
+    }
+    
+    /// Acceptor for the currently loaded certificate
+    pub fn acceptor(&self) -> TlsAcceptor {
+        self.acceptor.read().unwrap().clone()
+    }
+    
+    /// Reload the certificate and key from disk
+    ///
+    /// On failure the previous certificate stays in use.
+    pub fn reload(&self) -> Result<()> {
+        let acceptor = Self::build_acceptor(&self.config)?;
+        *self.acceptor.write().unwrap() = acceptor;
+        *self.modified.lock().unwrap() = Self::last_modified(&self.config);
+        
+        info!("Reloaded TLS certificate from {}", self.config.cert_path.display());
+        Ok(())
+    }
+    
+    /// Reload if the certificate or key changed on disk since the last load
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let modified = Self::last_modified(&self.config);
+        if modified.is_none() || modified == *self.modified.lock().unwrap() {
+            return Ok(false);
+        }
+        
+        self.reload()?;
+        Ok(true)
+    }
+    
+    /// Reload certificates on SIGHUP and whenever the files change
+    pub fn spawn_reload_task(self: &Arc<Self>) {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(
+                store.config.reload_interval_secs.max(1),
+            ));
+            
+            #[cfg(unix)]
+            let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+                Ok(signal) => Some(signal),
+                Err(e) => {
+                    error!("Failed to install SIGHUP handler: {}", e);
+                    None
+                }
+            };
+            
+            loop {
+                #[cfg(unix)]
+                let reload = tokio::select! {
+                    _ = interval.tick() => store.reload_if_changed().map(|_| ()),
+                    Some(_) = async { hangup.as_mut()?.recv().await } => {
+                        info!("Received SIGHUP, reloading TLS certificate");
+                        store.reload()
+                    }
+                };
+                
+                #[cfg(not(unix))]
+                let reload = {
+                    interval.tick().await;
+                    store.reload_if_changed().map(|_| ())
+                };
+                
+                if let Err(e) = reload {
+                    error!("Failed to reload TLS certificate: {}", e);
+                }
+            }
+        });
+    }
+    
+    /// Build a TLS acceptor from PEM files
+    fn build_acceptor(config: &TlsConfig) -> Result<TlsAcceptor> {
+        let certs = rustls_pemfile::certs(&mut Self::open(&config.cert_path)?)
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| MediaSoupError::Tls(format!("Invalid certificate {}: {}", config.cert_path.display(), e)))?;
+        if certs.is_empty() {
+            return Err(MediaSoupError::Tls(format!("No certificates found in {}", config.cert_path.display())));
+        }
+        
+        let key = rustls_pemfile::private_key(&mut Self::open(&config.key_path)?)
+            .map_err(|e| MediaSoupError::Tls(format!("Invalid private key {}: {}", config.key_path.display(), e)))?
+            .ok_or_else(|| MediaSoupError::Tls(format!("No private key found in {}", config.key_path.display())))?;
+        
+        let server_config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .map_err(|e| MediaSoupError::Tls(e.to_string()))?;
+        
+        Ok(TlsAcceptor::from(Arc::new(server_config)))
+    }
+    
+    fn open(path: &Path) -> Result<BufReader<File>> {
+        File::open(path)
+            .map(BufReader::new)
+            .map_err(|e| MediaSoupError::Tls(format!("Failed to open {}: {}", path.display(), e)))
+    }
+    
+    /// Most recent modification time of the certificate and key files
+    fn last_modified(config: &TlsConfig) -> Option<SystemTime> {
+        [&config.cert_path, &config.key_path]
+            .iter()
+            .filter_map(|path| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+            .max()
+    }
+}
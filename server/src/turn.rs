@@ -0,0 +1,31 @@
+use crate::config::TurnConfig;
+use crate::signaling::IceServer;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Issue time-limited TURN credentials in the coturn REST API format
+///
+/// The username is `<expiry unix timestamp>:<user id>` and the credential is
+/// the base64 HMAC-SHA1 of the username keyed with the shared secret.
+pub fn issue_credentials(config: &TurnConfig, user_id: &str, now: SystemTime) -> IceServer {
+    let expires_at = now
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        + config.ttl_secs;
+    let username = format!("{}:{}", expires_at, user_id);
+    
+    let mut mac = Hmac::<Sha1>::new_from_slice(config.secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(username.as_bytes());
+    let credential = STANDARD.encode(mac.finalize().into_bytes());
+    
+    IceServer {
+        urls: config.urls.clone(),
+        username,
+        credential,
+    }
+}
@@ -1,15 +1,35 @@
+use mediasoup_server::limits::RateLimiter;
+use mediasoup_server::room::Peer;
+use mediasoup_server::signaling::OutgoingFrame;
+use mediasoup_server::tls::CertificateStore;
 use mediasoup_server::{Config, MediaSoupServer, SignalingMessage};
 use serde_json::json;
+use std::path::Path;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::mpsc::Receiver;
 use tokio::time::timeout;
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 
-#[tokio::test]
-async fn test_server_startup() {
-    // Create a test configuration
-    let config = Config {
+/// A peer with default limits and the receiving end of its outgoing queue
+fn test_peer(user_id: &str, queue_size: usize) -> (Peer, Receiver<OutgoingFrame>) {
+    let (sender, receiver) = tokio::sync::mpsc::channel(queue_size);
+    let peer = Peer::new(
+        user_id.to_string(),
+        sender,
+        Duration::from_secs(60),
+        RateLimiter::new(20.0, 50),
+        RateLimiter::new(10.0, 20),
+    );
+    (peer, receiver)
+}
+
+/// A configuration with one worker on loopback and default limits
+fn test_config() -> Config {
+    Config {
         listen_addr: "127.0.0.1:0".parse().unwrap(), // Use random port
         http_addr: None,
+        tls: None,
         worker: mediasoup_server::config::WorkerConfig {
             num_workers: 1,
             log_level: "error".to_string(),
@@ -25,14 +45,117 @@ async fn test_server_startup() {
                 ip: "127.0.0.1".to_string(),
                 announced_ip: None,
             }],
+            enable_udp: true,
+            enable_tcp: true,
+            prefer_udp: true,
+            transport_connect_timeout_secs: 30,
+            transport_idle_timeout_secs: 300,
+            max_incoming_bitrate: None,
+            max_outgoing_bitrate: None,
+            room_outgoing_bitrate: None,
+            webrtc_server: None,
         },
-    };
+        signaling: mediasoup_server::config::SignalingConfig {
+            outgoing_queue_size: 64,
+            slow_peer_timeout_ms: 1000,
+            allowed_origins: vec![],
+            max_connections: 100,
+            max_connections_per_ip: 10,
+            handshake_timeout_ms: 10000,
+        },
+        limits: mediasoup_server::config::LimitsConfig {
+            max_message_size: 65536,
+            requests_per_second: 20.0,
+            request_burst: 50,
+            max_violations: 20,
+            max_relay_payload_size: 16384,
+            relay_messages_per_second: 10.0,
+            relay_burst: 20,
+            max_transports_per_peer: 4,
+            max_producers_per_peer: 8,
+            max_consumers_per_peer: 200,
+            max_data_producers_per_peer: 4,
+            max_data_consumers_per_peer: 100,
+        },
+        room: mediasoup_server::config::RoomConfig {
+            last_n: None,
+            active_speaker_interval_ms: 300,
+            audio_level_interval_ms: 500,
+            audio_level_threshold: -70,
+            key_frame_request_interval_ms: 1000,
+            duplicate_sessions: mediasoup_server::config::DuplicateSessionPolicy::Replace,
+            lobby: false,
+            password: None,
+            max_peers: None,
+            max_video_publishers: None,
+            video_publish_roles: vec![
+                mediasoup_server::signaling::PeerRole::Player,
+                mediasoup_server::signaling::PeerRole::Gm,
+            ],
+            show_spectators: false,
+        },
+        turn: None,
+        auth: None,
+        admin: None,
+    }
+}
+
+#[tokio::test]
+async fn test_server_startup() {
+    let config = test_config();
     
     // Test that server can be created
     let server = MediaSoupServer::new(config).await;
     assert!(server.is_ok(), "Failed to create MediaSoup server");
 }
 
+#[test]
+fn test_webrtc_server_listen_infos() {
+    use mediasoup_server::config::{ListenIp, WebRtcServerConfig};
+    use mediasoup_server::server::webrtc_server_listen_infos;
+    use mediasoup::data_structures::Protocol;
+    
+    let listen_ips = vec![
+        ListenIp { ip: "0.0.0.0".to_string(), announced_ip: Some("203.0.113.7".to_string()) },
+        ListenIp { ip: "::".to_string(), announced_ip: None },
+    ];
+    let server_config = WebRtcServerConfig { udp_port: Some(40000), tcp_port: Some(40000) };
+    
+    // Worker 1 listens on the base port + 1 for every IP and protocol
+    let ports: Vec<_> = webrtc_server_listen_infos(&listen_ips, &server_config, 1)
+        .unwrap()
+        .iter()
+        .map(|info| (info.ip.to_string(), info.protocol, info.port))
+        .collect();
+    assert_eq!(ports, vec![
+        ("0.0.0.0".to_string(), Protocol::Udp, Some(40001)),
+        ("0.0.0.0".to_string(), Protocol::Tcp, Some(40001)),
+        ("::".to_string(), Protocol::Udp, Some(40001)),
+        ("::".to_string(), Protocol::Tcp, Some(40001)),
+    ]);
+    
+    let udp_only = WebRtcServerConfig { udp_port: Some(65535), tcp_port: None };
+    assert_eq!(webrtc_server_listen_infos(&listen_ips, &udp_only, 0).unwrap().len(), 2);
+    assert!(webrtc_server_listen_infos(&listen_ips, &udp_only, 1).is_err());
+}
+
+#[tokio::test]
+async fn test_webrtc_server_listens_on_configured_port() {
+    use mediasoup_server::config::WebRtcServerConfig;
+    use mediasoup_server::server::CustomWorkerManager;
+    
+    // Find a free port, then have the worker's WebRtcServer take it
+    let port = std::net::UdpSocket::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let mut config = test_config();
+    config.webrtc.webrtc_server = Some(WebRtcServerConfig { udp_port: Some(port), tcp_port: Some(port) });
+    
+    let workers = CustomWorkerManager::new(&config).await.expect("Failed to create worker");
+    let (_, webrtc_server) = workers.get_worker().await.unwrap();
+    assert!(webrtc_server.is_some());
+    assert!(std::net::UdpSocket::bind(("127.0.0.1", port)).is_err());
+    assert!(std::net::TcpListener::bind(("127.0.0.1", port)).is_err());
+}
+
 #[tokio::test]
 async fn test_signaling_message_serialization() {
     let message = SignalingMessage::request(
@@ -67,4 +190,980 @@ async fn test_signaling_notification() {
     assert!(notification.is_notification());
     assert!(!notification.is_request());
     assert_eq!(notification.method, "newProducer");
+}
+
+#[tokio::test]
+async fn test_peer_outgoing_queue_backpressure() {
+    let (peer, mut receiver) = test_peer("user-1", 1);
+    
+    peer.send_message(SignalingMessage::notification("newProducer".to_string(), None))
+        .expect("First message should fit in the queue");
+    assert_eq!(peer.queue_depth(), 1);
+    
+    // Lossy notifications are dropped silently while the queue is full
+    peer.send_message(SignalingMessage::notification("audioLevels".to_string(), None))
+        .expect("Lossy notification should be dropped without error");
+    
+    // Anything else overflows the queue and is reported as an error
+    let result = peer.send_message(SignalingMessage::notification("producerClosed".to_string(), None));
+    assert!(result.is_err(), "Critical message should not be dropped silently");
+    
+    let stats = peer.queue_stats();
+    assert_eq!(stats.capacity, 1);
+    assert_eq!(stats.high_water_mark, 1);
+    assert_eq!(stats.dropped, 2);
+    
+    // The overflow requested a disconnect
+    timeout(Duration::from_secs(1), peer.disconnect_requested())
+        .await
+        .expect("Peer should have been asked to disconnect");
+    
+    assert_eq!(&*receiver.recv().await.unwrap().method, "newProducer");
+    assert_eq!(peer.queue_depth(), 0);
+}
+
+#[test]
+fn test_rate_limiter_burst_and_refill() {
+    let start = std::time::Instant::now();
+    let mut limiter = mediasoup_server::limits::RateLimiter::new(10.0, 3);
+    
+    for _ in 0..3 {
+        assert!(limiter.try_acquire_at(start), "Burst requests should be allowed");
+    }
+    assert!(!limiter.try_acquire_at(start), "Request beyond the burst should be rejected");
+    
+    // 10 requests/sec refills one token every 100ms
+    assert!(limiter.try_acquire_at(start + Duration::from_millis(100)));
+    assert!(!limiter.try_acquire_at(start + Duration::from_millis(100)));
+    
+    // Tokens never accumulate beyond the burst size
+    let later = start + Duration::from_secs(60);
+    for _ in 0..3 {
+        assert!(limiter.try_acquire_at(later));
+    }
+    assert!(!limiter.try_acquire_at(later));
+}
+
+#[test]
+fn test_connection_tracker_limits() {
+    use mediasoup_server::limits::{ConnectionRejection, ConnectionTracker};
+    
+    let tracker = ConnectionTracker::new(3, 2);
+    let first_ip = "192.0.2.1".parse().unwrap();
+    let second_ip = "192.0.2.2".parse().unwrap();
+    
+    let a = tracker.try_acquire(first_ip).unwrap();
+    let _b = tracker.try_acquire(first_ip).unwrap();
+    assert_eq!(
+        tracker.try_acquire(first_ip).unwrap_err(),
+        ConnectionRejection::TooManyConnectionsFromIp
+    );
+    
+    let _c = tracker.try_acquire(second_ip).unwrap();
+    assert_eq!(
+        tracker.try_acquire(second_ip).unwrap_err(),
+        ConnectionRejection::TooManyConnections
+    );
+    assert_eq!(tracker.total(), 3);
+    
+    // Dropping a guard frees its slot
+    drop(a);
+    assert_eq!(tracker.total(), 2);
+    assert_eq!(tracker.count_for_ip(first_ip), 1);
+    assert!(tracker.try_acquire(second_ip).is_ok());
+}
+
+#[tokio::test]
+async fn test_stalled_handshake_releases_connection_slot() {
+    use mediasoup_server::limits::{complete_handshake, ConnectionTracker};
+    
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let tracker = ConnectionTracker::new(1, 1);
+    
+    // A client that opens TCP and never sends the upgrade request
+    let _client = tokio::net::TcpStream::connect(addr).await.unwrap();
+    let (stream, peer_addr) = listener.accept().await.unwrap();
+    let guard = tracker.try_acquire(peer_addr.ip()).unwrap();
+    
+    let upgrade = tokio_tungstenite::accept_async(stream);
+    assert!(complete_handshake(Duration::from_millis(50), upgrade).await.is_none());
+    drop(guard);
+    assert_eq!(tracker.total(), 0);
+    assert!(tracker.try_acquire(peer_addr.ip()).is_ok());
+}
+
+#[tokio::test]
+async fn test_websocket_message_size_limit() {
+    use futures_util::{SinkExt, StreamExt};
+    use mediasoup_server::limits::websocket_config;
+    use tokio_tungstenite::tungstenite::protocol::Role;
+    use tokio_tungstenite::tungstenite::Error;
+    use tokio_tungstenite::WebSocketStream;
+    
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    let mut client = WebSocketStream::from_raw_socket(client, Role::Client, None).await;
+    let mut server = WebSocketStream::from_raw_socket(server, Role::Server, Some(websocket_config(1024))).await;
+    
+    client.send(Message::Text("x".repeat(512))).await.unwrap();
+    match server.next().await {
+        Some(Ok(Message::Text(text))) => assert_eq!(text.len(), 512),
+        other => panic!("Expected a text message, got {:?}", other),
+    }
+    
+    // Over the limit the read fails and the connection is closed
+    client.send(Message::Text("x".repeat(4096))).await.unwrap();
+    assert!(matches!(server.next().await, Some(Err(Error::Capacity(_)))));
+}
+
+/// Whether a client trusting only `trusted_cert` completes a handshake with `store`
+async fn tls_handshake_succeeds(store: &CertificateStore, trusted_cert: &Path) -> bool {
+    use tokio_rustls::rustls::pki_types::ServerName;
+    use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+    use tokio_rustls::TlsConnector;
+    
+    let mut roots = RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut std::io::BufReader::new(std::fs::File::open(trusted_cert).unwrap())) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let connector = TlsConnector::from(Arc::new(
+        ClientConfig::builder().with_root_certificates(roots).with_no_client_auth(),
+    ));
+    
+    let (client, server) = tokio::io::duplex(16 * 1024);
+    let acceptor = store.acceptor();
+    tokio::spawn(async move { acceptor.accept(server).await });
+    connector.connect(ServerName::try_from("localhost").unwrap(), client).await.is_ok()
+}
+
+/// Move a file's modification time `seconds_ahead` into the future
+fn touch(path: &Path, seconds_ahead: u64) {
+    std::fs::File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_modified(std::time::SystemTime::now() + Duration::from_secs(seconds_ahead))
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_certificate_store_reload() {
+    use mediasoup_server::config::TlsConfig;
+    
+    let fixtures = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let dir = std::env::temp_dir().join(format!("mediasoup-tls-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = TlsConfig {
+        cert_path: dir.join("cert.pem"),
+        key_path: dir.join("key.pem"),
+        reload_interval_secs: 60,
+    };
+    
+    // A missing certificate fails the first load
+    assert!(CertificateStore::load(config.clone()).is_err());
+    
+    std::fs::copy(fixtures.join("first-cert.pem"), &config.cert_path).unwrap();
+    std::fs::copy(fixtures.join("first-key.pem"), &config.key_path).unwrap();
+    let store = CertificateStore::load(config.clone()).unwrap();
+    assert!(tls_handshake_succeeds(&store, &fixtures.join("first-cert.pem")).await);
+    assert!(!store.reload_if_changed().unwrap());
+    
+    // A renewed certificate and key are picked up
+    std::fs::copy(fixtures.join("second-cert.pem"), &config.cert_path).unwrap();
+    std::fs::copy(fixtures.join("second-key.pem"), &config.key_path).unwrap();
+    touch(&config.cert_path, 10);
+    assert!(store.reload_if_changed().unwrap());
+    assert!(tls_handshake_succeeds(&store, &fixtures.join("second-cert.pem")).await);
+    assert!(!tls_handshake_succeeds(&store, &fixtures.join("first-cert.pem")).await);
+    
+    // A broken renewal keeps the previous certificate in use
+    std::fs::write(&config.cert_path, "not a certificate").unwrap();
+    touch(&config.cert_path, 20);
+    assert!(store.reload_if_changed().is_err());
+    assert!(tls_handshake_succeeds(&store, &fixtures.join("second-cert.pem")).await);
+    
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_origin_allowlist() {
+    use mediasoup_server::limits::is_origin_allowed;
+    
+    let allowed = vec!["https://foundry.example.com".to_string()];
+    assert!(is_origin_allowed(&allowed, Some("https://foundry.example.com")));
+    assert!(is_origin_allowed(&allowed, Some("https://FOUNDRY.example.com/")));
+    assert!(!is_origin_allowed(&allowed, Some("https://evil.example.com")));
+    assert!(!is_origin_allowed(&allowed, None));
+    
+    // No allowlist accepts any origin
+    assert!(is_origin_allowed(&[], None));
+    assert!(is_origin_allowed(&["*".to_string()], Some("https://anywhere.example")));
+}
+
+#[test]
+fn test_parse_listen_ips() {
+    let listen_ips = Config::parse_listen_ips("0.0.0.0=203.0.113.5, ::=2001:db8::1 ,192.168.1.10");
+    
+    assert_eq!(listen_ips.len(), 3);
+    assert_eq!(listen_ips[0].ip, "0.0.0.0");
+    assert_eq!(listen_ips[0].announced_ip.as_deref(), Some("203.0.113.5"));
+    assert_eq!(listen_ips[1].ip, "::");
+    assert_eq!(listen_ips[1].announced_ip.as_deref(), Some("2001:db8::1"));
+    assert_eq!(listen_ips[2].ip, "192.168.1.10");
+    assert_eq!(listen_ips[2].announced_ip, None);
+}
+
+#[test]
+fn test_turn_credentials() {
+    let config = mediasoup_server::config::TurnConfig {
+        urls: vec!["turn:turn.example.com:3478?transport=udp".to_string()],
+        secret: "coturn-secret".to_string(),
+        ttl_secs: 86400,
+    };
+    let now = std::time::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    
+    let ice_server = mediasoup_server::turn::issue_credentials(&config, "user-1", now);
+    
+    assert_eq!(ice_server.urls, config.urls);
+    assert_eq!(ice_server.username, "1700086400:user-1");
+    assert_eq!(ice_server.credential, "lXh0Vy/Y52xnrT2VD5kcpy1FOmE=");
+}
+
+
+#[test]
+fn test_transport_state_notification() {
+    use mediasoup::data_structures::{DtlsState, IceState};
+    
+    let (peer, mut receiver) = test_peer("user-1", 4);
+    
+    peer.notify_transport_state("transport-1", Some(IceState::Disconnected), None)
+        .expect("Notification should be queued");
+    peer.notify_transport_state("transport-1", None, Some(DtlsState::Failed))
+        .expect("Notification should be queued");
+    
+    let frame = receiver.try_recv().expect("ICE state notification should be sent");
+    let message: serde_json::Value = serde_json::from_str(&frame.text).unwrap();
+    assert_eq!(message["method"], "transportStateChanged");
+    assert_eq!(message["data"]["transportId"], "transport-1");
+    assert_eq!(message["data"]["iceState"], "disconnected");
+    assert!(message["data"]["dtlsState"].is_null());
+    
+    let frame = receiver.try_recv().expect("DTLS state notification should be sent");
+    let message: serde_json::Value = serde_json::from_str(&frame.text).unwrap();
+    assert_eq!(message["data"]["dtlsState"], "failed");
+}
+
+#[test]
+fn test_transport_watchdogs() {
+    use mediasoup::data_structures::{DtlsState, IceState};
+    use mediasoup_server::room::{should_close_unconnected, TransportTimeout};
+    
+    let (peer, _receiver) = test_peer("user-1", 4);
+    
+    // A transport the client never connected survives the connect timer...
+    assert!(!peer.connect_requested("recv"));
+    let connect_requested = peer.connect_requested("recv");
+    assert!(!should_close_unconnected(TransportTimeout::Connect, connect_requested, IceState::New, DtlsState::New));
+    
+    // ...but is reclaimed by the idle timer started at creation
+    assert!(should_close_unconnected(TransportTimeout::Idle, connect_requested, IceState::New, DtlsState::New));
+    assert_eq!(TransportTimeout::Idle.reason(), "idleTimeout");
+    
+    // Once connecting, the idle timer leaves it to the connect timer
+    assert!(peer.mark_connect_requested("send"));
+    assert!(!peer.mark_connect_requested("send"), "Only the first connect arms the watchdog");
+    let connect_requested = peer.connect_requested("send");
+    assert!(!should_close_unconnected(TransportTimeout::Idle, connect_requested, IceState::New, DtlsState::New));
+    
+    // It must be connected when the connect timer fires
+    assert!(should_close_unconnected(TransportTimeout::Connect, connect_requested, IceState::New, DtlsState::New));
+    assert!(should_close_unconnected(TransportTimeout::Connect, true, IceState::Connected, DtlsState::Connecting));
+    assert!(!should_close_unconnected(TransportTimeout::Connect, true, IceState::Completed, DtlsState::Connected));
+    
+    // An ICE-disconnected transport that isn't back by then is closed
+    assert!(should_close_unconnected(TransportTimeout::Connect, true, IceState::Disconnected, DtlsState::Connected));
+}
+
+#[test]
+fn test_outgoing_bitrate_split() {
+    use mediasoup_server::room::OutgoingBitratePolicy;
+    
+    let policy = OutgoingBitratePolicy { max_per_transport: None, room_budget: Some(1_000_000) };
+    assert_eq!(policy.split(&[1, 3]), Some(vec![250_000, 750_000]));
+    
+    let capped = OutgoingBitratePolicy { max_per_transport: Some(500_000), ..policy };
+    assert_eq!(capped.split(&[1, 3]), Some(vec![250_000, 500_000]));
+    
+    // A share below mediasoup's minimum is raised to it and paid for by the others
+    let tight = OutgoingBitratePolicy { max_per_transport: None, room_budget: Some(100_000) };
+    assert_eq!(tight.split(&[1, 9]), Some(vec![30_000, 70_000]));
+    
+    // Only a budget below the minimum for every transport is exceeded
+    let starved = OutgoingBitratePolicy { max_per_transport: None, room_budget: Some(50_000) };
+    assert_eq!(starved.split(&[1, 1]), Some(vec![30_000, 30_000]));
+    
+    assert_eq!(OutgoingBitratePolicy::default().split(&[1]), None);
+}
+
+#[test]
+fn test_outgoing_bitrate_rebalance() {
+    use mediasoup_server::room::OutgoingBitratePolicy;
+    use std::collections::HashMap;
+    
+    let policy = OutgoingBitratePolicy { max_per_transport: Some(600_000), room_budget: Some(1_000_000) };
+    let counts = HashMap::from([("a".to_string(), 1), ("b".to_string(), 3)]);
+    let mut changes = policy.rebalance(&counts, &HashMap::new());
+    changes.sort();
+    assert_eq!(changes, vec![("a".to_string(), 250_000), ("b".to_string(), 600_000)]);
+    
+    // Unchanged shares are not applied again
+    let applied: HashMap<String, u32> = changes.into_iter().collect();
+    assert!(policy.rebalance(&counts, &applied).is_empty());
+    
+    // A transport that lost its last consumer goes back to the per-transport cap
+    let counts = HashMap::from([("a".to_string(), 1)]);
+    let mut changes = policy.rebalance(&counts, &applied);
+    changes.sort();
+    assert_eq!(changes, vec![("a".to_string(), 600_000), ("b".to_string(), 600_000)]);
+    
+    // Without a cap it is unlimited again
+    let uncapped = OutgoingBitratePolicy { max_per_transport: None, ..policy };
+    let changes = uncapped.rebalance(&HashMap::new(), &HashMap::from([("b".to_string(), 250_000)]));
+    assert_eq!(changes, vec![("b".to_string(), 0)]);
+}
+
+#[test]
+fn test_clamp_incoming_bitrate() {
+    use mediasoup_server::room::clamp_incoming_bitrate;
+    
+    assert_eq!(clamp_incoming_bitrate(500_000, Some(1_000_000)), 500_000);
+    assert_eq!(clamp_incoming_bitrate(2_000_000, Some(1_000_000)), 1_000_000);
+    // 0 would lift mediasoup's limit entirely
+    assert_eq!(clamp_incoming_bitrate(0, Some(1_000_000)), 1_000_000);
+    assert_eq!(clamp_incoming_bitrate(0, None), 0);
+}
+
+#[test]
+fn test_validate_encodings() {
+    use mediasoup::rtp_parameters::{MediaKind, RtpParameters};
+    use mediasoup_server::room::validate_encodings;
+    
+    let parameters = |encodings: serde_json::Value| RtpParameters {
+        encodings: serde_json::from_value(encodings).unwrap(),
+        ..RtpParameters::default()
+    };
+    
+    // Single encodings, including SVC, are fine
+    assert!(validate_encodings(MediaKind::Audio, &parameters(json!([{ "ssrc": 1111 }]))).is_ok());
+    assert!(validate_encodings(MediaKind::Video, &parameters(json!([{ "ssrc": 1111, "scalabilityMode": "L3T3" }]))).is_ok());
+    
+    // Simulcast with unique rids and temporal layers only
+    let simulcast = parameters(json!([
+        { "rid": "r0", "scalabilityMode": "L1T3", "maxBitrate": 150000 },
+        { "rid": "r1", "scalabilityMode": "L1T3", "maxBitrate": 500000 },
+        { "rid": "r2", "scalabilityMode": "L1T3", "maxBitrate": 1500000 },
+    ]));
+    assert!(validate_encodings(MediaKind::Video, &simulcast).is_ok());
+    assert!(validate_encodings(MediaKind::Audio, &simulcast).is_err());
+    
+    assert!(validate_encodings(MediaKind::Video, &parameters(json!([]))).is_err());
+    assert!(validate_encodings(MediaKind::Video, &parameters(json!([{ "rid": "r0" }, { "rid": "r0" }]))).is_err());
+    assert!(validate_encodings(MediaKind::Video, &parameters(json!([{ "rid": "r0" }, { "ssrc": 2222 }]))).is_err());
+    assert!(validate_encodings(MediaKind::Video, &parameters(json!([{ "rid": "r0" }, { "maxBitrate": 500000 }]))).is_err());
+    assert!(validate_encodings(
+        MediaKind::Video,
+        &parameters(json!([{ "rid": "r0", "scalabilityMode": "L2T3" }, { "rid": "r1" }])),
+    ).is_err());
+    assert!(validate_encodings(
+        MediaKind::Video,
+        &parameters(json!([{ "rid": "r0" }, { "rid": "r1" }, { "rid": "r2" }, { "rid": "r3" }, { "rid": "r4" }])),
+    ).is_err());
+}
+
+#[test]
+fn test_select_last_n() {
+    use mediasoup_server::room::select_last_n;
+    
+    let order: Vec<String> = ["a", "b", "c", "d"].iter().map(|id| id.to_string()).collect();
+    let publishers: std::collections::HashSet<String> = order.iter().cloned().collect();
+    
+    let selected = select_last_n(&order, &publishers, "a", 2);
+    assert_eq!(selected.len(), 2);
+    assert!(selected.contains("b") && selected.contains("c"));
+    
+    // A peer never counts itself among the speakers it receives
+    let selected = select_last_n(&order, &publishers, "c", 2);
+    assert!(selected.contains("a") && selected.contains("b"));
+    
+    assert_eq!(select_last_n(&order, &publishers, "a", 10).len(), 3);
+    assert!(select_last_n(&order, &publishers, "a", 0).is_empty());
+    
+    // Peers without video don't take up slots
+    let publishers: std::collections::HashSet<String> = ["a", "c", "d"].iter().map(|id| id.to_string()).collect();
+    let selected = select_last_n(&order, &publishers, "a", 2);
+    assert!(selected.contains("c") && selected.contains("d"));
+}
+
+#[test]
+fn test_peer_last_n_override() {
+    use mediasoup_server::room::select_last_n;
+    
+    let order: Vec<String> = ["a", "b", "c", "d"].iter().map(|id| id.to_string()).collect();
+    let publishers: std::collections::HashSet<String> = order.iter().cloned().collect();
+    let (peer, _receiver) = test_peer("user-1", 4);
+    let forwarded = |room_last_n| {
+        peer.effective_last_n(room_last_n)
+            .map(|last_n| select_last_n(&order, &publishers, "a", last_n).len())
+    };
+    
+    // Without a peer setting the room's N applies
+    assert_eq!(forwarded(None), None);
+    assert_eq!(forwarded(Some(2)), Some(2));
+    
+    // The peer's N overrides it, and each change takes effect
+    peer.set_last_n(Some(1), Vec::new());
+    assert_eq!(forwarded(Some(2)), Some(1));
+    peer.set_last_n(Some(3), Vec::new());
+    assert_eq!(forwarded(Some(2)), Some(3));
+    peer.set_last_n(Some(0), Vec::new());
+    assert_eq!(forwarded(None), Some(0));
+    
+    // Clearing it falls back to the room again
+    peer.set_last_n(None, Vec::new());
+    assert_eq!(forwarded(Some(2)), Some(2));
+}
+
+#[tokio::test]
+async fn test_spawn_ordered_keeps_event_order() {
+    use std::sync::Mutex;
+    
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let (done_sender, mut done_receiver) = tokio::sync::mpsc::unbounded_channel();
+    let sender = mediasoup_server::room::spawn_ordered(&tokio::runtime::Handle::current(), {
+        let delivered = delivered.clone();
+        move |volumes: Vec<i8>| {
+            let delivered = delivered.clone();
+            let done_sender = done_sender.clone();
+            async move {
+                // Levels take longer to deliver than the silence that follows them
+                if !volumes.is_empty() {
+                    tokio::time::sleep(Duration::from_millis(20)).await;
+                }
+                delivered.lock().unwrap().push(volumes);
+                let _ = done_sender.send(());
+            }
+        }
+    });
+    
+    sender.send(vec![-40]).unwrap();
+    sender.send(vec![-50]).unwrap();
+    sender.send(Vec::new()).unwrap();
+    for _ in 0..3 {
+        timeout(Duration::from_secs(1), done_receiver.recv()).await.unwrap();
+    }
+    
+    assert_eq!(*delivered.lock().unwrap(), vec![vec![-40], vec![-50], Vec::new()]);
+}
+
+#[test]
+fn test_keyed_throttle() {
+    use mediasoup_server::limits::KeyedThrottle;
+    use std::time::Instant;
+    
+    let throttle = KeyedThrottle::new(Duration::from_secs(1));
+    let start = Instant::now();
+    
+    assert!(throttle.try_acquire_at("producer-1", start));
+    assert!(!throttle.try_acquire_at("producer-1", start + Duration::from_millis(500)));
+    
+    // Other keys are throttled independently
+    assert!(throttle.try_acquire_at("producer-2", start + Duration::from_millis(500)));
+    
+    assert!(throttle.try_acquire_at("producer-1", start + Duration::from_secs(1)));
+    
+    throttle.remove("producer-2");
+    assert!(throttle.try_acquire_at("producer-2", start + Duration::from_millis(600)));
+}
+
+#[test]
+fn test_resumes_each_get_a_key_frame() {
+    use mediasoup_server::limits::{Deferral, KeyedThrottle};
+    use std::sync::Mutex;
+    use std::time::Instant;
+    
+    // Two consumers of one producer resumed back to back: the second request
+    // is sent when the interval ends rather than dropped
+    let throttle = KeyedThrottle::new(Duration::from_secs(1));
+    let start = Instant::now();
+    assert_eq!(throttle.acquire_or_defer_at("producer-1", start), Deferral::Now);
+    assert_eq!(
+        throttle.acquire_or_defer_at("producer-1", start + Duration::from_millis(200)),
+        Deferral::After(Duration::from_millis(800)),
+    );
+    
+    // Later resumes in the interval are covered by the scheduled request
+    assert_eq!(
+        throttle.acquire_or_defer_at("producer-1", start + Duration::from_millis(500)),
+        Deferral::Scheduled,
+    );
+    assert!(!throttle.try_acquire_at("producer-1", start + Duration::from_millis(900)));
+    
+    // The interval restarts from the scheduled request
+    assert_eq!(
+        throttle.acquire_or_defer_at("producer-1", start + Duration::from_millis(1500)),
+        Deferral::After(Duration::from_millis(500)),
+    );
+    assert_eq!(throttle.acquire_or_defer_at("producer-1", start + Duration::from_secs(5)), Deferral::Now);
+    
+    // A last-N change resuming every viewer at once sends one request now and one later
+    let throttle = KeyedThrottle::new(Duration::from_secs(1));
+    let outcomes = Mutex::new(Vec::new());
+    std::thread::scope(|scope| {
+        for _ in 0..16 {
+            scope.spawn(|| outcomes.lock().unwrap().push(throttle.acquire_or_defer("producer-1")));
+        }
+    });
+    let outcomes = outcomes.into_inner().unwrap();
+    assert_eq!(outcomes.iter().filter(|outcome| **outcome == Deferral::Now).count(), 1);
+    assert_eq!(outcomes.iter().filter(|outcome| matches!(outcome, Deferral::After(_))).count(), 1);
+}
+
+#[test]
+fn test_peer_info_update() {
+    use mediasoup_server::signaling::PeerDevices;
+    
+    let (peer, _receiver) = test_peer("user-1", 4);
+    
+    let info = serde_json::to_value(peer.info()).unwrap();
+    assert_eq!(info, json!({
+        "userId": "user-1",
+        "displayName": null,
+        "role": "player",
+        "devices": { "audio": false, "video": false },
+    }));
+    
+    peer.update_info(Some("Alice".to_string()), None).unwrap();
+    let info = peer.update_info(None, Some(PeerDevices { audio: true, video: false })).unwrap();
+    assert_eq!(info.display_name.as_deref(), Some("Alice"));
+    assert!(info.devices.audio && !info.devices.video);
+}
+
+#[test]
+fn test_peer_relay_limit() {
+    let (peer, _receiver) = test_peer("user-1", 4);
+    
+    let message = peer.relay_message(false, json!({ "dice": [3, 6] }), 1024).unwrap();
+    assert_eq!(message.method, "peerMessage");
+    assert_eq!(message.data, Some(json!({
+        "fromUserId": "user-1",
+        "broadcast": false,
+        "payload": { "dice": [3, 6] },
+    })));
+    
+    // Oversized payloads are refused without spending a relay token
+    let error = peer.relay_message(true, json!({ "drawing": "x".repeat(2048) }), 1024).unwrap_err();
+    assert_eq!(error.code(), "LIMIT_EXCEEDED");
+    
+    // The relay burst is separate from the request limit
+    for _ in 1..20 {
+        peer.relay_message(true, json!({ "ping": [1, 2] }), 1024).unwrap();
+    }
+    let error = peer.relay_message(true, json!({ "ping": [1, 2] }), 1024).unwrap_err();
+    assert_eq!(error.code(), "RATE_LIMITED");
+    assert!(peer.receive_request(r#"{"id":"1","method":"getPeers"}"#, 20).unwrap().1);
+}
+
+#[tokio::test]
+async fn test_malformed_frames_disconnect_peer() {
+    // Malformed frames spend request tokens like valid ones
+    let (peer, _receiver) = test_peer("user-1", 4);
+    for _ in 0..50 {
+        assert_eq!(peer.receive_request("{not json", 1000).unwrap_err().code(), "INVALID_REQUEST");
+    }
+    let (message, allowed) = peer.receive_request(r#"{"id":"1","method":"getPeers"}"#, 1000).unwrap();
+    assert_eq!(message.method, "getPeers");
+    assert!(!allowed);
+    
+    // And a flood of them ends in a disconnect
+    let (peer, _receiver) = test_peer("user-2", 4);
+    for _ in 0..2 {
+        peer.receive_request("{not json", 3).unwrap_err();
+    }
+    assert!(timeout(Duration::from_millis(20), peer.disconnect_requested()).await.is_err());
+    peer.receive_request("{not json", 3).unwrap_err();
+    assert!(timeout(Duration::from_millis(20), peer.disconnect_requested()).await.is_ok());
+}
+
+#[test]
+fn test_join_token() {
+    use mediasoup_server::auth::{issue_token, query_param, verify_token, AuthClaims};
+    use mediasoup_server::signaling::PeerRole;
+    use std::time::UNIX_EPOCH;
+    
+    let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let claims = AuthClaims {
+        user_id: "gm-1".to_string(),
+        role: PeerRole::Gm,
+        display_name: Some("Game Master".to_string()),
+        exp: 1_700_000_060,
+    };
+    let token = issue_token("join-secret", &claims).unwrap();
+    
+    let verified = verify_token("join-secret", &token, now).unwrap();
+    assert_eq!(verified.user_id, "gm-1");
+    assert_eq!(verified.role, PeerRole::Gm);
+    assert_eq!(verified.display_name.as_deref(), Some("Game Master"));
+    
+    assert!(verify_token("other-secret", &token, now).is_err());
+    assert!(verify_token("join-secret", &token, now + Duration::from_secs(60)).is_err());
+    let (payload, signature) = token.split_once('.').unwrap();
+    assert!(verify_token("join-secret", &format!("{}x.{}", payload, signature), now).is_err());
+    
+    let query = format!("room=default&token={}&password=dragon%20%26+dice", token);
+    assert_eq!(query_param(Some(&query), "token"), Some(token.clone()));
+    assert_eq!(query_param(Some(&query), "password").as_deref(), Some("dragon & dice"));
+    assert_eq!(query_param(Some("token="), "token"), None);
+    assert_eq!(query_param(None, "token"), None);
+}
+
+#[test]
+fn test_lobby_admission() {
+    use mediasoup_server::config::DuplicateSessionPolicy;
+    use mediasoup_server::room::{password_matches, select_admitted};
+    use mediasoup_server::signaling::PeerRole;
+    
+    assert!(password_matches(None, PeerRole::Player, None));
+    assert!(password_matches(Some("dragon"), PeerRole::Player, Some("dragon")));
+    assert!(!password_matches(Some("dragon"), PeerRole::Player, Some("goblin")));
+    assert!(!password_matches(Some("dragon"), PeerRole::Spectator, None));
+    assert!(password_matches(Some("dragon"), PeerRole::Gm, None));
+    
+    let sessions: Vec<Arc<Peer>> = (0..3)
+        .map(|_| {
+            std::thread::sleep(Duration::from_millis(1));
+            Arc::new(test_peer("user-1", 1).0)
+        })
+        .collect();
+    let ids: Vec<String> = sessions.iter().map(|peer| peer.id.clone()).collect();
+    
+    // Replace admits the newest session, as a direct join would leave it
+    let mut shuffled = sessions.clone();
+    shuffled.rotate_left(1);
+    let (admitted, others) = select_admitted(DuplicateSessionPolicy::Replace, shuffled).unwrap();
+    assert_eq!(admitted.id, ids[2]);
+    assert_eq!(others.iter().map(|peer| peer.id.clone()).collect::<Vec<_>>(), ids[..2]);
+    
+    // Reject admits the oldest and turns the rest away
+    let (admitted, others) = select_admitted(DuplicateSessionPolicy::Reject, sessions).unwrap();
+    assert_eq!(admitted.id, ids[0]);
+    assert_eq!(others.len(), 2);
+    
+    assert!(select_admitted(DuplicateSessionPolicy::Replace, Vec::new()).is_none());
+}
+
+#[tokio::test]
+async fn test_admin_api_requires_token() {
+    use mediasoup_server::config::AdminConfig;
+    use mediasoup_server::room::BanList;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    let config = AdminConfig { token: "admin-token".to_string() };
+    let rooms = std::sync::Arc::new(dashmap::DashMap::new());
+    let addr = mediasoup_server::admin::spawn("127.0.0.1:0".parse().unwrap(), &config, rooms, BanList::new()).unwrap();
+    
+    let request = |authorization: &'static str| async move {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "GET /admin/rooms/default/bans HTTP/1.1\r\nHost: localhost\r\n{}Connection: close\r\n\r\n",
+            authorization
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+    
+    assert!(request("").await.starts_with("HTTP/1.1 401"));
+    assert!(request("Authorization: Bearer wrong\r\n").await.starts_with("HTTP/1.1 401"));
+    assert!(request("Authorization: Bearer admin-token\r\n").await.starts_with("HTTP/1.1 200"));
+}
+
+#[tokio::test]
+async fn test_admin_ban_before_room_exists() {
+    use mediasoup_server::config::AdminConfig;
+    use mediasoup_server::room::BanList;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    let config = AdminConfig { token: "admin-token".to_string() };
+    let rooms = std::sync::Arc::new(dashmap::DashMap::new());
+    let bans = BanList::new();
+    let addr = mediasoup_server::admin::spawn("127.0.0.1:0".parse().unwrap(), &config, rooms, bans.clone()).unwrap();
+    
+    let request = |method: &'static str, path: &'static str| async move {
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer admin-token\r\nConnection: close\r\n\r\n",
+            method, path
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+    
+    // No room has been created, yet the ban is recorded for when it is
+    assert!(request("PUT", "/admin/rooms/default/bans/user-1").await.starts_with("HTTP/1.1 204"));
+    assert!(bans.is_banned("default", "user-1"));
+    assert!(request("GET", "/admin/rooms/default/bans").await.contains(r#"{"userIds":["user-1"]}"#));
+    
+    assert!(request("DELETE", "/admin/rooms/default/bans/user-1").await.starts_with("HTTP/1.1 204"));
+    assert!(!bans.is_banned("default", "user-1"));
+    assert!(request("DELETE", "/admin/rooms/default/bans/user-1").await.starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn test_ban_survives_room_close() {
+    use mediasoup_server::room::BanList;
+    
+    // The server keeps bans by room ID; closing a room only drops the `Room`,
+    // and the next room with that ID shares the same list
+    let bans = BanList::new();
+    assert!(bans.ban("default", "user-1"));
+    assert!(!bans.ban("default", "user-1"));
+    assert!(bans.ban("default", "user-2"));
+    
+    assert!(bans.is_banned("default", "user-1"));
+    assert!(!bans.is_banned("other", "user-1"));
+    assert_eq!(bans.banned_user_ids("default"), vec!["user-1", "user-2"]);
+    assert!(bans.banned_user_ids("other").is_empty());
+    
+    assert!(bans.unban("default", "user-1"));
+    assert!(!bans.unban("default", "user-1"));
+    assert!(!bans.unban("other", "user-2"));
+    assert_eq!(bans.banned_user_ids("default"), vec!["user-2"]);
+}
+
+#[tokio::test]
+async fn test_admin_queue_metrics() {
+    use mediasoup_server::config::AdminConfig;
+    use mediasoup_server::room::BanList;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    
+    let config = AdminConfig { token: "admin-token".to_string() };
+    let rooms = std::sync::Arc::new(dashmap::DashMap::new());
+    let addr = mediasoup_server::admin::spawn("127.0.0.1:0".parse().unwrap(), &config, rooms, BanList::new()).unwrap();
+    
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    stream.write_all(
+        b"GET /admin/metrics HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer admin-token\r\nConnection: close\r\n\r\n",
+    ).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains("# TYPE mediasoup_outgoing_queue_depth gauge"));
+    assert!(response.contains("# TYPE mediasoup_outgoing_messages_dropped_total counter"));
+}
+
+#[test]
+fn test_error_codes() {
+    use mediasoup_server::signaling::SignalingError;
+    use mediasoup_server::MediaSoupError;
+    
+    let error = MediaSoupError::RoomFull("Room default is limited to 6 peers".to_string());
+    assert_eq!(error.code(), "ROOM_FULL");
+    assert_eq!(MediaSoupError::PublishNotAllowed("video".to_string()).code(), "PUBLISH_NOT_ALLOWED");
+    assert_eq!(MediaSoupError::PublishLimit("video".to_string()).code(), "PUBLISH_LIMIT");
+    assert_eq!(MediaSoupError::Config("bad".to_string()).code(), "INTERNAL_ERROR");
+    assert_eq!(MediaSoupError::Tls("handshake".to_string()).code(), "INTERNAL_ERROR");
+    assert_eq!(MediaSoupError::Forbidden("banned".to_string()).code(), "FORBIDDEN");
+    assert_eq!(MediaSoupError::LimitExceeded("producers".to_string()).code(), "LIMIT_EXCEEDED");
+    
+    let payload = serde_json::to_value(SignalingError {
+        error: error.to_string(),
+        code: Some(error.code().to_string()),
+    }).unwrap();
+    assert_eq!(payload, json!({
+        "error": "Room full: Room default is limited to 6 peers",
+        "code": "ROOM_FULL",
+    }));
+}
+
+#[test]
+fn test_spectator_role() {
+    use mediasoup_server::room::{check_can_publish, is_visible_to};
+    use mediasoup_server::signaling::PeerRole;
+    
+    let (peer, _receiver) = test_peer("viewer-1", 4);
+    peer.set_role(PeerRole::Spectator);
+    
+    assert_eq!(peer.role(), PeerRole::Spectator);
+    assert_eq!(serde_json::to_value(peer.info()).unwrap()["role"], "spectator");
+    assert_eq!(serde_json::from_value::<PeerRole>(json!("spectator")).unwrap(), PeerRole::Spectator);
+    
+    // Hidden from players unless configured, always shown to GMs
+    assert!(!is_visible_to(PeerRole::Spectator, PeerRole::Player, false));
+    assert!(is_visible_to(PeerRole::Spectator, PeerRole::Player, true));
+    assert!(is_visible_to(PeerRole::Spectator, PeerRole::Gm, false));
+    assert!(is_visible_to(PeerRole::Player, PeerRole::Spectator, false));
+    
+    // Refused produce, produceData and producing transports alike
+    assert_eq!(check_can_publish(&peer).unwrap_err().code(), "PUBLISH_NOT_ALLOWED");
+    
+    // Relaying would reveal the spectator through `fromUserId`
+    let error = peer.relay_message(true, json!({ "type": "chat" }), 1024).unwrap_err();
+    assert_eq!(error.code(), "PUBLISH_NOT_ALLOWED");
+    
+    peer.set_role(PeerRole::Player);
+    assert!(check_can_publish(&peer).is_ok());
+    assert!(peer.relay_message(true, json!({ "type": "chat" }), 1024).is_ok());
+}
+
+#[test]
+fn test_send_to_peer_skips_hidden_peers() {
+    use mediasoup_server::room::relay_targets;
+    use mediasoup_server::signaling::PeerRole;
+    
+    let (player, _player_receiver) = test_peer("player-1", 4);
+    let (gm, _gm_receiver) = test_peer("gm-1", 4);
+    gm.set_role(PeerRole::Gm);
+    let (spectator, _spectator_receiver) = test_peer("viewer-1", 4);
+    spectator.set_role(PeerRole::Spectator);
+    let peers = vec![Arc::new(spectator), Arc::new(gm)];
+    
+    // A hidden spectator looks the same to players as a user who isn't there
+    assert!(relay_targets(peers.clone(), &player, "viewer-1", false).is_empty());
+    assert!(relay_targets(peers.clone(), &player, "nobody", false).is_empty());
+    
+    assert_eq!(relay_targets(peers.clone(), &player, "viewer-1", true).len(), 1);
+    assert_eq!(relay_targets(peers.clone(), &peers[1], "viewer-1", false).len(), 1);
+    assert_eq!(relay_targets(peers, &player, "gm-1", false).len(), 1);
+}
+
+#[test]
+fn test_producer_limit() {
+    use mediasoup_server::room::check_producer_limit;
+    
+    // Checked under the room's publish lock, so concurrent produce requests
+    // see each other's producers
+    let (peer, _receiver) = test_peer("user-1", 4);
+    assert!(check_producer_limit(&peer, 1).is_ok());
+    assert_eq!(check_producer_limit(&peer, 0).unwrap_err().code(), "LIMIT_EXCEEDED");
+}
+
+#[test]
+fn test_token_display_name_is_locked() {
+    use mediasoup_server::signaling::PeerDevices;
+    
+    let (peer, _receiver) = test_peer("user-1", 4);
+    
+    // Without a token name the client picks its own
+    assert_eq!(peer.update_info(Some("Alice".to_string()), None).unwrap().display_name.as_deref(), Some("Alice"));
+    
+    peer.set_token_display_name("Bob".to_string());
+    let error = peer.update_info(Some("Mallory".to_string()), None).unwrap_err();
+    assert_eq!(error.code(), "FORBIDDEN");
+    assert_eq!(peer.info().display_name.as_deref(), Some("Bob"));
+    
+    // Devices can still be updated, and repeating the token's name is not a change
+    let info = peer.update_info(Some("Bob".to_string()), Some(PeerDevices { audio: true, video: false })).unwrap();
+    assert_eq!(info.display_name.as_deref(), Some("Bob"));
+    assert!(info.devices.audio);
+}
+
+#[test]
+fn test_moderation_requires_gm() {
+    use mediasoup_server::room::require_gm;
+    use mediasoup_server::signaling::PeerRole;
+    
+    let (peer, _receiver) = test_peer("user-1", 4);
+    
+    // muteUser, unmuteUser, stopUserVideo, kickUser and closeRoom all check this first
+    assert_eq!(require_gm(&peer).unwrap_err().code(), "FORBIDDEN");
+    peer.set_role(PeerRole::Spectator);
+    assert_eq!(require_gm(&peer).unwrap_err().code(), "FORBIDDEN");
+    peer.set_role(PeerRole::Gm);
+    assert!(require_gm(&peer).is_ok());
+}
+
+#[tokio::test]
+async fn test_moderation_effect_on_target() {
+    use mediasoup_server::signaling::ModerationAction;
+    
+    fn moderated(receiver: &mut Receiver<OutgoingFrame>) -> serde_json::Value {
+        let frame = receiver.try_recv().expect("moderated should be sent");
+        let message: serde_json::Value = serde_json::from_str(&frame.text).unwrap();
+        assert_eq!(message["method"], "moderated");
+        message["data"].clone()
+    }
+    
+    let (target, mut receiver) = test_peer("player-1", 8);
+    
+    // Mute blocks audio until unmuted, without disconnecting
+    let producer_ids = target.mute().await.unwrap();
+    assert!(target.is_muted());
+    target.moderate(ModerationAction::Mute, Some("gm-1".to_string()), producer_ids).unwrap();
+    let data = moderated(&mut receiver);
+    assert_eq!(data["action"], "mute");
+    assert_eq!(data["byUserId"], "gm-1");
+    
+    target.unmute();
+    assert!(!target.is_muted());
+    target.moderate(ModerationAction::Unmute, Some("gm-1".to_string()), Vec::new()).unwrap();
+    assert_eq!(moderated(&mut receiver)["action"], "unmute");
+    
+    // Stopping video reports the closed producers
+    target.moderate(ModerationAction::StopVideo, Some("gm-1".to_string()), vec!["producer-1".to_string()]).unwrap();
+    let data = moderated(&mut receiver);
+    assert_eq!(data["action"], "stopVideo");
+    assert_eq!(data["producerIds"], json!(["producer-1"]));
+    assert!(timeout(Duration::from_millis(20), target.disconnect_requested()).await.is_err());
+    
+    // Kicks and closed rooms disconnect the target after telling it why
+    target.moderate(ModerationAction::Kick, Some("gm-1".to_string()), Vec::new()).unwrap();
+    assert_eq!(moderated(&mut receiver)["action"], "kick");
+    assert!(timeout(Duration::from_millis(20), target.disconnect_requested()).await.is_ok());
+    
+    let (target, mut receiver) = test_peer("player-2", 8);
+    target.moderate(ModerationAction::CloseRoom, Some("gm-1".to_string()), Vec::new()).unwrap();
+    assert_eq!(moderated(&mut receiver)["action"], "closeRoom");
+    assert!(timeout(Duration::from_millis(20), target.disconnect_requested()).await.is_ok());
+}
+
+#[test]
+fn test_data_channel_signaling() {
+    use mediasoup_server::error::MediaSoupError;
+    use mediasoup_server::room::check_not_own;
+    use mediasoup_server::signaling::{DataProducersResponse, NewDataProducerNotification, ProduceDataData};
+    
+    let data: ProduceDataData = serde_json::from_value(json!({
+        "transportId": "transport-1",
+        "sctpStreamParameters": { "streamId": 0, "ordered": true },
+    })).unwrap();
+    assert_eq!(data.label, "");
+    assert!(data.app_data.is_none());
+    
+    let response = serde_json::to_value(DataProducersResponse {
+        data_producers: vec![NewDataProducerNotification {
+            id: "data-producer-1".to_string(),
+            user_id: "user-2".to_string(),
+            label: "pings".to_string(),
+            protocol: "json".to_string(),
+            app_data: None,
+        }],
+    }).unwrap();
+    assert_eq!(response["dataProducers"][0]["userId"], "user-2");
+    
+    // Data producers get their own not-found code
+    let error = MediaSoupError::DataProducerNotFound("data-producer-1".to_string());
+    assert_eq!(error.code(), "DATA_PRODUCER_NOT_FOUND");
+    
+    // Data consumers have their own cap, apart from media consumers
+    let error = MediaSoupError::DataConsumerLimit("Maximum of 100 data consumers per peer".to_string());
+    assert_eq!(error.code(), "DATA_CONSUMER_LIMIT");
+    
+    // A peer can't consume what it produces itself
+    let (peer, _receiver) = test_peer("user-1", 1);
+    assert_eq!(check_not_own(&peer.id, &peer, "data producer").unwrap_err().code(), "INVALID_REQUEST");
+    assert!(check_not_own("other-peer", &peer, "data producer").is_ok());
 }
\ No newline at end of file